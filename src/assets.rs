@@ -0,0 +1,170 @@
+use std::{
+	cell::RefCell,
+	path::{Path, PathBuf},
+	sync::{
+		Arc, LazyLock,
+		atomic::{AtomicUsize, Ordering},
+	},
+};
+
+use hashbrown::{HashMap, HashSet};
+use macroquad::prelude::*;
+use parking_lot::RwLock;
+
+use crate::{
+	audio,
+	utils::{load_img_blocking, spawn_blocking},
+};
+
+type ImageCache = HashMap<PathBuf, Image>;
+
+/// Decoded images, shared between every race.
+static IMAGES: LazyLock<RwLock<ImageCache>> = LazyLock::new(|| RwLock::new(HashMap::new()));
+
+thread_local! {
+	/// Textures can only be created and used on the main thread.
+	static TEXTURES: RefCell<HashMap<PathBuf, Texture2D>> = RefCell::new(HashMap::new());
+}
+
+/// Tracks assets being loaded in the background.
+pub struct Progress {
+	total: usize,
+	done: Arc<AtomicUsize>,
+}
+
+/// Starts loading images and sounds in parallel, in the background.
+/// Duplicates, and anything that has already been loaded, are skipped.
+pub fn preload(
+	images: impl IntoIterator<Item = PathBuf>,
+	sounds: impl IntoIterator<Item = PathBuf>,
+) -> Progress {
+	let images = {
+		let cache = IMAGES.read();
+		images
+			.into_iter()
+			.filter(|path| !cache.contains_key(path))
+			.collect::<HashSet<_>>()
+	};
+	let sounds = sounds
+		.into_iter()
+		.filter(|path| !audio::is_loaded(path))
+		.collect::<HashSet<_>>();
+
+	let done = Arc::new(AtomicUsize::new(0));
+	let total = images.len() + sounds.len();
+
+	for path in images {
+		let done = done.clone();
+		spawn_blocking(move || {
+			let img = load_img_blocking(&path);
+			IMAGES.write().insert(path, img);
+			done.fetch_add(1, Ordering::Release);
+		});
+	}
+	for path in sounds {
+		let done = done.clone();
+		spawn_blocking(move || {
+			audio::preload(&path);
+			done.fetch_add(1, Ordering::Release);
+		});
+	}
+
+	Progress { total, done }
+}
+
+/// Gets an image, loading it now if it was not preloaded.
+pub fn image(path: impl AsRef<Path>) -> Image {
+	let path = path.as_ref();
+	if let Some(img) = IMAGES.read().get(path) {
+		return img.clone();
+	}
+
+	let img = load_img_blocking(path);
+	IMAGES.write().insert(path.into(), img.clone());
+	img
+}
+
+/// Gets a texture, creating it from the image if this is the first time it was used.
+/// Must be called on the main thread.
+pub fn texture(path: impl AsRef<Path>) -> Texture2D {
+	let path = path.as_ref();
+
+	TEXTURES.with_borrow_mut(|textures| {
+		textures
+			.entry_ref(path)
+			.or_insert_with(|| Texture2D::from_image(&image(path)))
+			.clone()
+	})
+}
+
+/// Drops an image, and its texture, so it is loaded again the next time it is used.
+/// Must be called on the main thread.
+pub fn forget(path: impl AsRef<Path>) {
+	let path = path.as_ref();
+	IMAGES.write().remove(path);
+	TEXTURES.with_borrow_mut(|textures| textures.remove(path));
+}
+
+impl Progress {
+	/// Waits for `total` more things, which are counted as done by adding to the returned counter.
+	pub fn add(&mut self, total: usize) -> Arc<AtomicUsize> {
+		self.total += total;
+		self.done.clone()
+	}
+
+	pub fn is_done(&self) -> bool {
+		self.done.load(Ordering::Acquire) >= self.total
+	}
+
+	/// How much has been loaded, from 0 to 1.
+	pub fn fraction(&self) -> f32 {
+		if self.total == 0 {
+			return 1.;
+		}
+		self.done.load(Ordering::Acquire) as f32 / self.total as f32
+	}
+
+	/// Draws a loading screen with a progress bar.
+	pub fn draw(&self) {
+		clear_background(BLACK);
+
+		let (width, height) = (screen_width(), screen_height());
+		let size = vec2(width * 0.6, height * 0.04);
+		let pos = vec2(width, height) / 2. - size / 2.;
+
+		draw_rectangle_lines(pos.x, pos.y, size.x, size.y, 2., WHITE);
+		draw_rectangle(pos.x, pos.y, size.x * self.fraction(), size.y, WHITE);
+
+		draw_text(
+			&format!(
+				"Loading... {}/{}",
+				self.done.load(Ordering::Acquire),
+				self.total
+			),
+			pos.x,
+			pos.y - size.y * 0.5,
+			(width + height) / 70.,
+			WHITE,
+		);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn preload_dedupes() {
+		let path = std::env::temp_dir().join("openhrt_preload_dedupes.png");
+		Image::gen_image_color(4, 2, RED).export_png(path.to_str().unwrap());
+
+		let progress = preload([path.clone(), path.clone()], []);
+		assert_eq!(progress.total, 1);
+		while !progress.is_done() {
+			std::thread::yield_now();
+		}
+
+		assert_eq!(preload([path.clone()], []).total, 0);
+		assert_eq!(image(&path).width(), 4);
+	}
+}
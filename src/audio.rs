@@ -1,6 +1,10 @@
 use hashbrown::HashMap;
 use parking_lot::RwLock;
-use std::{fmt::Debug, path::Path, sync::LazyLock};
+use std::{
+	fmt::Debug,
+	path::{Path, PathBuf},
+	sync::LazyLock,
+};
 
 use kira::{
 	AudioManager, AudioManagerSettings,
@@ -11,77 +15,105 @@ use kira::{
 	},
 };
 
+use crate::error::{Error, Result, warn};
+
 pub type StreamHandle = StreamingSoundHandle<FromFileError>;
 type Global<T> = LazyLock<RwLock<T>>;
-type AudioCache = HashMap<String, StaticSoundData>;
+/// Sounds that failed to load are stored as `None`, so they are only reported once.
+type AudioCache = HashMap<PathBuf, Option<StaticSoundData>>;
 
-static MANAGER: Global<AudioManager> = manager();
+/// `None` if there is no audio device, in which case everything is silent.
+static MANAGER: LazyLock<Option<RwLock<AudioManager>>> = manager();
 static AUDIO: Global<AudioCache> = audio();
 
-pub fn stream<Dir: AsRef<Path> + Debug>(path: Dir) -> StreamHandle {
-	let data = match StreamingSoundData::from_file(&path) {
-		Ok(ok) => ok,
-		Err(e) => {
-			tracing::error!("Failed to stream file {path:?}: {e}");
-			panic!()
-		}
-	};
+/// Streams audio from a file.
+/// Returns `None`, and stays silent, if it cannot be played.
+pub fn stream<Dir: AsRef<Path> + Debug>(path: Dir) -> Option<StreamHandle> {
+	let manager = MANAGER.as_ref()?;
 
-	match MANAGER.write().play(data) {
+	let result = StreamingSoundData::from_file(&path)
+		.map_err(|e| Error::Audio(path.as_ref().into(), e))
+		.and_then(|data| {
+			manager
+				.write()
+				.play(data)
+				.map_err(|e| Error::play(path.as_ref(), e))
+		});
+
+	match result {
 		Ok(ok) => {
 			tracing::info!("Audio {path:?} is streaming!");
-			ok
+			Some(ok)
 		}
-		Err(_) => {
-			todo!("Handle stream playing errors")
+		Err(e) => {
+			warn(&e);
+			None
 		}
 	}
 }
 
-pub fn play_or_load(key: &str) -> Option<StaticSoundHandle> {
-	let mut writer = AUDIO.write();
+/// Plays a sound, loading it into the cache if it has not been played before.
+/// Returns `None`, and stays silent, if it cannot be played.
+pub fn play_or_load(path: impl AsRef<Path>) -> Option<StaticSoundHandle> {
+	play(path.as_ref(), |data| data)
+}
 
-	let data = if let Some(data) = writer.get(key) {
-		data.clone()
-	} else {
-		let data = read(key);
-		writer.insert(key.into(), data.clone());
-		data
-	};
+/// Plays a sound over and over until its handle is stopped, loading it into the cache like [`play_or_load`].
+pub fn play_looped(path: impl AsRef<Path>) -> Option<StaticSoundHandle> {
+	play(path.as_ref(), |data| data.loop_region(..))
+}
 
-	match MANAGER.write().play(data) {
-		Ok(ok) => Some(ok),
-		Err(e) => {
-			tracing::warn!("Failed to play audio: {e}");
-			None
-		}
+fn play(
+	path: &Path,
+	settings: impl FnOnce(StaticSoundData) -> StaticSoundData,
+) -> Option<StaticSoundHandle> {
+	let manager = MANAGER.as_ref()?;
+
+	preload(path);
+	let data = AUDIO.read().get(path).cloned().flatten()?;
+
+	manager
+		.write()
+		.play(settings(data))
+		.map_err(|_| Error::SoundLimit(path.into()))
+		.inspect_err(warn)
+		.ok()
+}
+
+/// Loads a sound into the cache, if it is not already there.
+pub fn preload(path: impl AsRef<Path>) {
+	let path = path.as_ref();
+	if is_loaded(path) {
+		return;
 	}
+
+	let data = read(path).inspect_err(warn).ok();
+	AUDIO.write().insert(path.into(), data);
+}
+
+/// Checks if a sound is in the cache, even if it failed to load.
+pub fn is_loaded(path: impl AsRef<Path>) -> bool {
+	AUDIO.read().contains_key(path.as_ref())
+}
+
+/// Loads a sound from a file, without playing it.
+pub fn read(path: impl AsRef<Path>) -> Result<StaticSoundData> {
+	let path = path.as_ref();
+	let data = StaticSoundData::from_file(path).map_err(|e| Error::Audio(path.into(), e))?;
+
+	tracing::info!("Audio {path:?} loaded!");
+	Ok(data)
 }
 
-const fn manager() -> Global<AudioManager> {
+const fn manager() -> LazyLock<Option<RwLock<AudioManager>>> {
 	LazyLock::new(|| {
-		RwLock::new(
-			AudioManager::new(AudioManagerSettings::default()).unwrap_or_else(|e| {
-				tracing::error!("Could not create audio manager: {e}");
-				panic!()
-			}),
-		)
+		AudioManager::new(AudioManagerSettings::default())
+			.map(RwLock::new)
+			.map_err(|e| warn(&Error::AudioManager(e)))
+			.ok()
 	})
 }
 
 const fn audio() -> Global<AudioCache> {
 	LazyLock::new(|| RwLock::new(HashMap::new()))
 }
-
-fn read(path: &str) -> StaticSoundData {
-	match StaticSoundData::from_file(path) {
-		Ok(ok) => {
-			tracing::info!("Audio \"{path}\" loaded!");
-			ok
-		}
-		Err(e) => {
-			tracing::error!("Failed to load audio from \"{path}\": {e}");
-			panic!()
-		}
-	}
-}
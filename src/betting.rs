@@ -0,0 +1,359 @@
+use std::{collections::BTreeMap, ops::Range, path::PathBuf};
+
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	data::{self, read},
+	error::warn,
+	sim::{estimate::Estimate, horse::Horse},
+};
+
+/// How much a new player starts with.
+const STARTING_BALANCE: u64 = 1000;
+/// How much the stake changes with each press.
+const STAKE_STEP: u64 = 10;
+/// The cut kept by the house, taken out of every payout.
+const HOUSE_EDGE: f32 = 0.1;
+
+/// Every player's balance, kept in a file between races.
+pub struct Ledger {
+	path: PathBuf,
+	balances: Balances,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Balances {
+	players: BTreeMap<String, u64>,
+}
+
+/// How likely each horse is to win.
+pub struct Odds {
+	chances: Vec<f32>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BetKind {
+	/// Pays out if the horse comes first
+	Win,
+	/// Pays out if the horse comes first or second
+	Place,
+	/// Pays out if the horse comes first, second or third
+	Show,
+}
+
+/// A player's bet on the current race. Nothing is bet while the stake is 0.
+struct Slip {
+	player: String,
+	horse: usize,
+	kind: BetKind,
+	stake: u64,
+}
+
+/// Takes bets during the countdown, and pays them out once a horse wins.
+pub struct Betting {
+	ledger: Ledger,
+	odds: Odds,
+	slips: Vec<Slip>,
+	selected: usize,
+	locked: bool,
+	/// How much each player got back, once the race is settled
+	results: Vec<(String, u64, u64)>,
+}
+
+impl Ledger {
+	/// Loads balances from a file, adding any new players with the starting balance.
+	pub fn load(path: impl Into<PathBuf>, players: &[String]) -> Self {
+		let path = path.into();
+		let mut balances = if path.exists() {
+			read::<Balances>(&path)
+		} else {
+			Balances::default()
+		};
+
+		for player in players {
+			balances
+				.players
+				.entry(player.clone())
+				.or_insert(STARTING_BALANCE);
+		}
+
+		Self { path, balances }
+	}
+
+	pub fn save(&self) {
+		match data::write(&self.path, &self.balances) {
+			Ok(()) => tracing::info!("Balances saved to \"{}\"", self.path.display()),
+			Err(e) => warn(&e),
+		}
+	}
+
+	pub fn balance(&self, player: &str) -> u64 {
+		self.balances.players.get(player).copied().unwrap_or(0)
+	}
+}
+
+impl Odds {
+	/// The seeds of the races simulated to find the odds.
+	/// Always the same, so the same race always has the same odds.
+	pub const SEEDS: Range<u64> = 0..500;
+	/// How long each simulated race can go on for, in seconds.
+	pub const LIMIT: f32 = 600.;
+
+	/// Finds the odds from simulated races.
+	/// Every horse is counted as having won one extra race, so none are given no chance at all.
+	pub fn new(estimate: &Estimate) -> Self {
+		let races = (estimate.races + estimate.horses.len()) as f32;
+
+		Self {
+			chances: estimate
+				.horses
+				.iter()
+				.map(|horse| (horse.wins + 1) as f32 / races)
+				.collect(),
+		}
+	}
+
+	/// Gives every horse the same chance, for when the races could not be simulated.
+	pub fn even(horses: usize) -> Self {
+		Self {
+			chances: vec![1. / horses.max(1) as f32; horses],
+		}
+	}
+
+	/// How much a winning bet is multiplied by. Never less than 1, so a winning bet never loses money.
+	pub fn payout(&self, horse: usize, kind: BetKind) -> f32 {
+		let chance = self.chances.get(horse).copied().unwrap_or(0.);
+		let chance = (chance * kind.places() as f32).clamp(f32::EPSILON, 1.);
+
+		((1. - HOUSE_EDGE) / chance).max(1.)
+	}
+
+	pub fn horses(&self) -> usize {
+		self.chances.len()
+	}
+}
+
+impl BetKind {
+	/// How many of the top places the horse can finish in to pay out.
+	pub fn places(self) -> usize {
+		match self {
+			Self::Win => 1,
+			Self::Place => 2,
+			Self::Show => 3,
+		}
+	}
+
+	fn next(self) -> Self {
+		match self {
+			Self::Win => Self::Place,
+			Self::Place => Self::Show,
+			Self::Show => Self::Win,
+		}
+	}
+
+	fn name(self) -> &'static str {
+		match self {
+			Self::Win => "win",
+			Self::Place => "place",
+			Self::Show => "show",
+		}
+	}
+}
+
+impl Betting {
+	pub fn new(ledger: Ledger, odds: Odds) -> Self {
+		let slips = ledger
+			.balances
+			.players
+			.keys()
+			.map(|player| Slip {
+				player: player.clone(),
+				horse: 0,
+				kind: BetKind::Win,
+				stake: 0,
+			})
+			.collect::<Vec<_>>();
+
+		if slips.is_empty() {
+			tracing::warn!("There are no players to take bets from");
+		}
+
+		Self {
+			ledger,
+			odds,
+			slips,
+			selected: 0,
+			locked: false,
+			results: vec![],
+		}
+	}
+
+	/// Changes the selected bet with the keyboard, until bets are locked.
+	pub fn update(&mut self) {
+		if self.locked || self.slips.is_empty() {
+			return;
+		}
+
+		let players = self.slips.len();
+		if is_key_pressed(KeyCode::Down) {
+			self.selected = (self.selected + 1) % players;
+		}
+		if is_key_pressed(KeyCode::Up) {
+			self.selected = (self.selected + players - 1) % players;
+		}
+
+		let horses = self.odds.horses().max(1);
+		let balance = self.ledger.balance(&self.slips[self.selected].player);
+		let slip = &mut self.slips[self.selected];
+
+		if is_key_pressed(KeyCode::Right) {
+			slip.horse = (slip.horse + 1) % horses;
+		}
+		if is_key_pressed(KeyCode::Left) {
+			slip.horse = (slip.horse + horses - 1) % horses;
+		}
+		if is_key_pressed(KeyCode::Tab) {
+			slip.kind = slip.kind.next();
+		}
+		if is_key_pressed(KeyCode::Equal) || is_key_pressed(KeyCode::KpAdd) {
+			slip.stake = (slip.stake + STAKE_STEP).min(balance);
+		}
+		if is_key_pressed(KeyCode::Minus) || is_key_pressed(KeyCode::KpSubtract) {
+			slip.stake = slip.stake.saturating_sub(STAKE_STEP);
+		}
+	}
+
+	/// Stops taking bets, and takes every stake out of its player's balance.
+	pub fn lock(&mut self) {
+		if self.locked {
+			return;
+		}
+		self.locked = true;
+
+		for slip in &self.slips {
+			if let Some(balance) = self.ledger.balances.players.get_mut(&slip.player) {
+				*balance = balance.saturating_sub(slip.stake);
+			}
+		}
+	}
+
+	/// Pays out every winning bet, given the index of every horse from first place to last, then saves the balances.
+	pub fn settle(&mut self, standings: &[usize]) {
+		self.lock();
+
+		for slip in self.slips.iter().filter(|slip| slip.stake > 0) {
+			let places = &standings[..slip.kind.places().min(standings.len())];
+			let payout = if places.contains(&slip.horse) {
+				(slip.stake as f32 * self.odds.payout(slip.horse, slip.kind)).floor() as u64
+			} else {
+				0
+			};
+
+			if let Some(balance) = self.ledger.balances.players.get_mut(&slip.player) {
+				*balance += payout;
+			}
+			self.results.push((slip.player.clone(), slip.stake, payout));
+		}
+
+		self.ledger.save();
+	}
+
+	/// Draws every player's bet, while bets are being taken.
+	pub fn draw(&self, horses: &[Horse]) {
+		let size = (screen_width() + screen_height()) / 90.;
+		let x = screen_width() * 0.55;
+		let mut y = size * 2.;
+
+		draw_text(
+			"Place your bets! Up/Down: player, Left/Right: horse, Tab: type, +/-: stake",
+			x,
+			y,
+			size,
+			WHITE,
+		);
+
+		for (i, slip) in self.slips.iter().enumerate() {
+			y += size * 1.5;
+			let horse = horses
+				.get(slip.horse)
+				.map_or("?", |horse| horse.win_data.name());
+			let color = if i == self.selected { YELLOW } else { WHITE };
+
+			draw_text(
+				&format!(
+					"{} ({}): {} on {horse} to {} ({:.1}x)",
+					slip.player,
+					self.ledger.balance(&slip.player),
+					slip.stake,
+					slip.kind.name(),
+					self.odds.payout(slip.horse, slip.kind),
+				),
+				x,
+				y,
+				size,
+				color,
+			);
+		}
+	}
+
+	/// Draws what every player won or lost, once the race is settled.
+	pub fn draw_results(&self) {
+		let size = (screen_width() + screen_height()) / 90.;
+		let x = screen_width() * 0.55;
+		let mut y = size * 2.;
+
+		for (player, stake, payout) in &self.results {
+			let (text, color) = if payout > stake {
+				(format!("{player} won {}", payout - stake), GREEN)
+			} else {
+				(format!("{player} lost {}", stake - payout), RED)
+			};
+
+			draw_text(&text, x, y, size, color);
+			y += size * 1.5;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn settle() {
+		let path = std::env::temp_dir().join("openhrt_settle.toml");
+		let _ = std::fs::remove_file(&path);
+
+		let players = ["a".to_string(), "b".to_string(), "c".to_string()];
+		let ledger = Ledger::load(&path, &players);
+		let odds = Odds {
+			chances: vec![0.6, 0.3, 0.1],
+		};
+
+		let mut betting = Betting::new(ledger, odds);
+		for (slip, kind) in
+			betting
+				.slips
+				.iter_mut()
+				.zip([BetKind::Win, BetKind::Place, BetKind::Show])
+		{
+			slip.horse = 2;
+			slip.kind = kind;
+			slip.stake = 100;
+		}
+		betting.settle(&[0, 2, 1]);
+
+		let ledger = Ledger::load(&path, &[]);
+		let payout = |kind| (100. * betting.odds.payout(2, kind)).floor() as u64;
+		assert_eq!(ledger.balance("a"), STARTING_BALANCE - 100);
+		assert_eq!(
+			ledger.balance("b"),
+			STARTING_BALANCE - 100 + payout(BetKind::Place)
+		);
+		assert_eq!(
+			ledger.balance("c"),
+			STARTING_BALANCE - 100 + payout(BetKind::Show)
+		);
+	}
+}
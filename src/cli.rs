@@ -0,0 +1,136 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+use tracing::Level;
+
+/// Horses bounce around an arena, racing to the carrots.
+#[derive(Parser)]
+#[command(version, about)]
+pub struct Args {
+	#[command(subcommand)]
+	pub mode: Option<Mode>,
+
+	#[command(flatten)]
+	pub race: RaceArgs,
+
+	#[command(flatten)]
+	pub window: WindowArgs,
+
+	/// Record the race, saving it to a replay file once a horse wins
+	#[arg(long, global = true, value_name = "FILE")]
+	pub record: Option<PathBuf>,
+
+	/// Play back a race from a replay file
+	#[arg(long, value_name = "FILE", conflicts_with = "record")]
+	pub replay: Option<PathBuf>,
+
+	/// Run every race in a season file, keeping standings between them
+	#[arg(long, value_name = "FILE", conflicts_with_all = ["record", "replay"])]
+	pub season: Option<PathBuf>,
+
+	/// Restart the race whenever its files change, and after it finishes
+	#[arg(long, conflicts_with_all = ["record", "replay", "season"])]
+	pub watch: bool,
+
+	/// Open the race file in the editor, to move the horses, gate and carrots
+	#[arg(long, conflicts_with_all = ["record", "replay", "season", "watch"])]
+	pub edit: bool,
+
+	/// Take bets from the players in this file during the countdown, and pay them out after the race.
+	/// The file is created if it does not exist
+	#[arg(
+		long,
+		value_name = "FILE",
+		num_args = 0..=1,
+		default_missing_value = "./data/players.toml",
+		conflicts_with_all = ["replay", "season", "watch", "edit", "skip_intro"]
+	)]
+	pub bets: Option<PathBuf>,
+
+	/// Add a player to the betting file, if they are not already in it
+	#[arg(long = "player", value_name = "NAME", requires = "bets")]
+	pub players: Vec<String>,
+
+	/// Where the careers of every horse are kept, and updated after every race
+	#[arg(
+		long,
+		global = true,
+		value_name = "FILE",
+		default_value = "./data/roster.toml"
+	)]
+	pub roster: PathBuf,
+
+	/// The most verbose log level to show
+	#[arg(long, global = true, default_value_t = Level::INFO)]
+	pub log_level: Level,
+}
+
+#[derive(Subcommand)]
+pub enum Mode {
+	/// Simulate a race without opening a window, then print the result
+	Simulate {
+		#[command(flatten)]
+		race: RaceArgs,
+
+		/// Give up after this many seconds of simulated time
+		#[arg(long, default_value_t = 600.)]
+		limit: f32,
+	},
+	/// Simulate many races without opening a window, then print how likely each horse is to win
+	Odds {
+		/// The race file to simulate
+		#[arg(default_value = "./data/race.toml")]
+		path: PathBuf,
+
+		/// How many races to simulate, each with a different seed
+		#[arg(long, default_value_t = 1000)]
+		races: u64,
+
+		/// Give up on a race after this many seconds of simulated time
+		#[arg(long, default_value_t = 600.)]
+		limit: f32,
+	},
+	/// Print the career of every horse that has raced
+	Roster,
+	/// Check a race file, and every file it references, for mistakes
+	Validate {
+		/// The race file to check
+		#[arg(default_value = "./data/race.toml")]
+		path: PathBuf,
+	},
+}
+
+/// Options merged over what the race file contains.
+#[derive(clap::Args, Clone)]
+pub struct RaceArgs {
+	/// The race file to load
+	#[arg(default_value = "./data/race.toml")]
+	pub path: PathBuf,
+
+	/// Override the seed in the race file
+	#[arg(long)]
+	pub seed: Option<u64>,
+
+	/// Override whether the intro is skipped
+	#[arg(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+	pub skip_intro: Option<bool>,
+
+	/// Override how many horses have to reach the carrots for the race to end, or 0 for every horse
+	#[arg(long, value_name = "COUNT")]
+	pub finishers: Option<usize>,
+}
+
+#[derive(clap::Args)]
+pub struct WindowArgs {
+	/// The width of the window
+	#[arg(long)]
+	pub width: Option<i32>,
+
+	/// The height of the window
+	#[arg(long)]
+	pub height: Option<i32>,
+
+	/// Start in fullscreen
+	#[arg(long)]
+	pub fullscreen: bool,
+}
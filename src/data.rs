@@ -1,58 +1,167 @@
 use std::{
 	fmt::Debug,
+	ops::Range,
 	path::{Path, PathBuf},
+	sync::{Arc, atomic::AtomicUsize},
 };
 
 use macroquad::prelude::*;
-use serde::{Deserialize, de::DeserializeOwned};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+pub use update::update;
+pub use validate::Report;
 
 use crate::{
+	assets,
 	audio::{StreamHandle, stream},
+	betting::Odds,
+	cli::RaceArgs,
+	editor::Editor,
+	error::{Error, Result, warn},
 	race::{
-		Race,
-		horse::Horse,
+		BOUNCE_SOUND, Loading, Race,
+		sprite::{Animation, Facing, Sprite},
 		victory::{Carrots, Victory},
 	},
-	utils::{load_img, load_img_blocking},
+	season::Season,
+	sim::{
+		Sim,
+		bounce::Bounce,
+		estimate::Estimate,
+		field::DistanceField,
+		geometry::{Geometry, Walls},
+		goal::Goal,
+		horse::DIR_WIDTH,
+		object::Object,
+	},
+	utils::load_img_blocking,
 };
 
-#[derive(Deserialize, Default)]
+mod update;
+mod validate;
+
+#[derive(Serialize, Deserialize, Default, Clone)]
 pub struct RaceData {
 	foreground: PathBuf,
 	background: PathBuf,
 	seed: Option<u64>,
 	skip_intro: Option<bool>,
+	/// How many horses have to reach the carrots for the race to end, or 0 for every horse
+	finishers: Option<usize>,
 	horses: Vec<(Vec2, PathBuf)>,
 	countdown: CountdownData,
 	gate: GateData,
 	carrots: CarrotData,
+	/// How the race is won, which is reaching the carrots if left out
+	#[serde(default, skip_serializing_if = "is_default")]
+	goal: Goal,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	objects: Vec<ObjectData>,
+	/// What horses bounce off of, which is the foreground if left out
+	#[serde(default, skip_serializing_if = "is_default")]
+	walls: Walls,
+	/// How horses bounce off of things, which is the classic random bounce if left out
+	#[serde(default, skip_serializing_if = "is_default")]
+	bounce: Bounce,
+	/// Sounds for every horse that does not set its own
+	#[serde(default, skip_serializing_if = "is_default")]
+	sounds: SoundData,
+	/// Looped in the background while the horses are running
+	music: Option<PathBuf>,
+	/// Looped along with the music, for the crowd watching
+	crowd: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct SeasonData {
+	races: Vec<PathBuf>,
+	/// Points awarded for each place in the standings at the end of a race, starting with first
+	#[serde(default = "SeasonData::default_points")]
+	points: Vec<u32>,
 }
 
 #[derive(Deserialize, Default)]
 pub struct HorseData {
 	sprite: PathBuf,
+	/// How the sprite is split into frames, if it is a sprite sheet
+	animation: Option<Animation>,
+	#[serde(default)]
+	facing: Facing,
 	win_data: WinData,
+	#[serde(default)]
+	stats: Stats,
+	#[serde(default)]
+	sounds: SoundData,
 }
 
-#[derive(Deserialize, Default)]
+/// The sounds a horse makes. Anything left out of a horse file is taken from the race file, then the default.
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+pub struct SoundData {
+	/// Played when the horse bounces off of a wall or object
+	bounce: Option<PathBuf>,
+	/// Played when the horse bounces off of another horse, which is the bounce sound if left out
+	hit: Option<PathBuf>,
+	/// Played when the horse wins, over its victory music
+	victory: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone)]
 pub struct GateData {
 	start: Vec2,
 	end: Vec2,
 	sprite: PathBuf,
 }
 
-#[derive(Deserialize, Default)]
+#[derive(Serialize, Deserialize, Default, Clone)]
 pub struct CountdownData {
 	audio: PathBuf,
 	sprite: PathBuf,
 }
 
-#[derive(Deserialize, Default)]
+#[derive(Serialize, Deserialize, Default, Clone)]
 pub struct CarrotData {
 	pos: Vec2,
 	sprite: PathBuf,
 }
 
+/// Something placed in the arena, and how it looks and sounds.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ObjectData {
+	#[serde(flatten)]
+	object: Object,
+	sprite: PathBuf,
+	/// Played whenever a horse triggers the object
+	sound: Option<PathBuf>,
+}
+
+/// A position in a race file that can be moved in the editor.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Point {
+	Horse(usize),
+	Carrots,
+	GateStart,
+	GateEnd,
+	/// A checkpoint or hazard of the goal
+	Goal(usize),
+	Object(usize),
+}
+
+/// How a horse moves. Anything left out of a horse file is the same for every horse.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct Stats {
+	/// The fastest a horse can go, in pixels per tick before doubling
+	pub top_speed: f32,
+	/// How much speed is gained every tick
+	pub acceleration: f32,
+	/// How much of a random speed between 1 and 2 is kept after bouncing
+	pub elasticity: f32,
+	/// How far from its center a horse collides with things, in pixels
+	pub size: f32,
+	/// How much every bounce is steered towards the carrots, from 0 to 1
+	pub luck: f32,
+}
+
 #[derive(Deserialize, Default, Clone)]
 pub struct WinData {
 	name: String,
@@ -65,42 +174,357 @@ impl RaceData {
 		read(path)
 	}
 
-	pub fn set_seed(self) -> Self {
-		macroquad::rand::srand(match self.seed {
-			Some(seed) => seed,
-			None => miniquad::date::now().to_bits(),
-		});
+	/// Seeds Macroquad's global RNG, and locks in the seed used by the simulation.
+	pub fn set_seed(mut self) -> Self {
+		let seed = self.seed.unwrap_or_else(|| miniquad::date::now().to_bits());
+		macroquad::rand::srand(seed);
+		self.seed = Some(seed);
 
 		self
 	}
 
-	pub async fn into_race(self) -> Race {
-		let foreground = load_img(self.foreground);
-		let background = load_img(self.background);
+	/// Merges options from the command line over the ones from the file.
+	pub fn with_overrides(mut self, args: &RaceArgs) -> Self {
+		if args.skip_intro.is_some() {
+			self.skip_intro = args.skip_intro;
+		}
+		if args.finishers.is_some() {
+			self.finishers = args.finishers;
+		}
+
+		self.override_seed(args.seed)
+	}
+
+	/// Plays the intro even if the file skips it, for anything that happens during the countdown.
+	pub fn with_intro(mut self) -> Self {
+		if self.skip_intro == Some(true) {
+			tracing::info!("Playing the intro anyway, so there is a countdown to bet during");
+		}
+		self.skip_intro = Some(false);
+		self
+	}
+
+	/// Replaces the seed from the file, if one is provided.
+	pub fn override_seed(mut self, seed: Option<u64>) -> Self {
+		if seed.is_some() {
+			self.seed = seed;
+		}
+
+		self
+	}
+
+	pub fn seed(&self) -> Option<u64> {
+		self.seed
+	}
+
+	/// Loads everything the simulation needs, without touching the GPU or audio.
+	pub fn into_sim(self) -> Sim {
+		let foreground = load_img_blocking(&self.foreground);
+		let geometry = self.walls.geometry(&foreground).map(Arc::new);
+		let horses = self
+			.horses
+			.iter()
+			.map(|(pos, path)| {
+				let horse = read::<HorseData>(path);
+				(*pos, horse.win_data, horse.stats)
+			})
+			.collect();
+
+		self.sim(foreground, horses, self.seed.unwrap_or_default(), geometry)
+	}
+
+	/// Sets up the simulation of the race, with the given horses.
+	fn sim(
+		&self,
+		arena: impl Into<Arc<Image>>,
+		horses: Vec<(Vec2, WinData, Stats)>,
+		seed: u64,
+		geometry: Option<Arc<Geometry>>,
+	) -> Sim {
+		let sim = Sim::new(arena, self.carrots.pos, horses, seed)
+			.with_finishers(self.finishers.unwrap_or(1))
+			.with_goal(self.goal.clone())
+			.with_bounce(self.bounce)
+			.with_objects(
+				self.objects
+					.iter()
+					.map(|data| data.object.clone())
+					.collect(),
+			);
+
+		match geometry {
+			Some(geometry) => sim.with_geometry(geometry),
+			None => sim,
+		}
+	}
+
+	/// The walls as an image, which is the foreground unless they are made of segments.
+	pub fn wall_image(&self, arena: &Image) -> Image {
+		match self.walls.geometry(arena) {
+			Some(geometry) => geometry.image(arena.width, arena.height),
+			None => arena.clone(),
+		}
+	}
+
+	/// Opens the race in the editor, which saves it back to `path`.
+	pub fn into_editor(self, path: impl Into<PathBuf>) -> Editor {
+		let sprites = self
+			.horses
+			.iter()
+			.map(|(_, path)| read::<HorseData>(path).sprite())
+			.collect();
+
+		Editor::new(
+			path.into(),
+			assets::image(&self.foreground),
+			assets::texture(&self.background),
+			sprites,
+			self.gate.texture(),
+			assets::texture(&self.carrots.sprite),
+			self,
+		)
+	}
+
+	pub fn horse_count(&self) -> usize {
+		self.horses.len()
+	}
+
+	/// The file of every horse, in order of id.
+	pub fn entrants(&self) -> Vec<PathBuf> {
+		self.horses.iter().map(|(_, path)| path.clone()).collect()
+	}
+
+	/// The name of every horse, read from their files.
+	pub fn horse_names(&self) -> Vec<String> {
+		self.horses
+			.iter()
+			.map(|(_, path)| read::<HorseData>(path).win_data.name)
+			.collect()
+	}
+
+	/// Simulates a race for every seed, to find how likely each horse is to win.
+	/// `done` counts every race as it finishes.
+	pub fn estimate(&self, seeds: Range<u64>, limit: f32, done: &AtomicUsize) -> Estimate {
+		let arena = Arc::new(assets::image(&self.foreground));
+		let geometry = self.walls.geometry(&arena).map(Arc::new);
+		let horses = self
+			.horses
+			.iter()
+			.map(|(pos, path)| (*pos, read::<HorseData>(path).stats))
+			.collect::<Vec<_>>();
+
+		Estimate::new(horses.len(), seeds, limit, done, |seed| {
+			let horses = horses
+				.iter()
+				.map(|(pos, stats)| (*pos, WinData::default(), *stats))
+				.collect();
+			self.sim(arena.clone(), horses, seed, geometry.clone())
+		})
+	}
+
+	/// The odds of each horse winning, for betting on.
+	/// `done` counts every simulated race as it finishes.
+	pub fn odds(&self, done: &AtomicUsize) -> Odds {
+		Odds::new(&self.estimate(Odds::SEEDS, Odds::LIMIT, done))
+	}
+
+	/// Every position that can be moved in the editor, and where it is.
+	pub fn points(&self) -> Vec<(Point, Vec2)> {
+		let mut points = vec![
+			(Point::GateStart, self.gate.start),
+			(Point::GateEnd, self.gate.end),
+			(Point::Carrots, self.carrots.pos),
+		];
+		points.extend(
+			self.horses
+				.iter()
+				.enumerate()
+				.map(|(i, (pos, _))| (Point::Horse(i), *pos)),
+		);
+		points.extend(
+			self.goal
+				.points()
+				.into_iter()
+				.enumerate()
+				.map(|(i, pos)| (Point::Goal(i), pos)),
+		);
+		points.extend(
+			self.objects
+				.iter()
+				.enumerate()
+				.map(|(i, data)| (Point::Object(i), data.object.pos)),
+		);
+		points
+	}
+
+	/// Where a position is, or `None` if there is no horse or goal point with that index.
+	pub fn point(&self, point: Point) -> Option<Vec2> {
+		match point {
+			Point::Horse(i) => self.horses.get(i).map(|(pos, _)| *pos),
+			Point::Carrots => Some(self.carrots.pos),
+			Point::GateStart => Some(self.gate.start),
+			Point::GateEnd => Some(self.gate.end),
+			Point::Goal(i) => self.goal.points().get(i).copied(),
+			Point::Object(i) => self.objects.get(i).map(|data| data.object.pos),
+		}
+	}
+
+	/// Gets a position to move it, or `None` if there is no horse or goal point with that index.
+	pub fn point_mut(&mut self, point: Point) -> Option<&mut Vec2> {
+		match point {
+			Point::Horse(i) => self.horses.get_mut(i).map(|(pos, _)| pos),
+			Point::Carrots => Some(&mut self.carrots.pos),
+			Point::GateStart => Some(&mut self.gate.start),
+			Point::GateEnd => Some(&mut self.gate.end),
+			Point::Goal(i) => self.goal.point_mut(i),
+			Point::Object(i) => self.objects.get_mut(i).map(|data| &mut data.object.pos),
+		}
+	}
+
+	/// Starts loading every image and sound the race uses, in the background.
+	pub fn preload(self) -> Loading {
+		let progress = assets::preload(self.images(), self.sounds());
+		Loading::new(progress, self)
+	}
+
+	/// Every file the race is built from, including itself.
+	pub fn files(&self, path: impl Into<PathBuf>) -> Vec<PathBuf> {
+		let mut files = vec![path.into()];
+		files.extend(self.horses.iter().map(|(_, path)| path.clone()));
+		files.extend(self.images());
+		files
+	}
+
+	/// Every image the race uses.
+	fn images(&self) -> Vec<PathBuf> {
+		let mut images = vec![
+			self.foreground.clone(),
+			self.background.clone(),
+			self.countdown.sprite.clone(),
+			self.gate.sprite.clone(),
+			self.carrots.sprite.clone(),
+		];
+		images.extend(self.objects.iter().map(|data| data.sprite.clone()));
+		for (_, path) in &self.horses {
+			let horse = read::<HorseData>(path);
+			images.push(horse.sprite);
+			images.push(horse.win_data.screen);
+		}
+		images
+	}
+
+	/// Every sound the race uses.
+	fn sounds(&self) -> Vec<PathBuf> {
+		let mut sounds = vec![BOUNCE_SOUND.into()];
+		sounds.extend(self.music.iter().chain(&self.crowd).cloned());
+		sounds.extend(self.sounds.paths().map(|(_, path)| path.to_path_buf()));
+		sounds.extend(self.objects.iter().filter_map(|data| data.sound.clone()));
+		for (_, path) in &self.horses {
+			let horse = read::<HorseData>(path);
+			sounds.extend(horse.sounds.paths().map(|(_, path)| path.to_path_buf()));
+		}
+		sounds
+	}
+
+	/// Builds the race, using assets from [`Self::preload`] where possible.
+	pub fn into_race(self) -> Race {
+		let seed = self.seed.unwrap_or_default();
 
 		let mut horses = vec![];
-		for (pos, path) in self.horses {
+		let mut sprites = vec![];
+		let mut entrants = vec![];
+		let mut sounds = vec![];
+		for (pos, path) in &self.horses {
 			let horse = read::<HorseData>(path);
-			horses.push(horse.into_horse(pos));
+			entrants.push(path.clone());
+			sprites.push(horse.sprite());
+			sounds.push(horse.sounds.or(&self.sounds));
+			horses.push((*pos, horse.win_data, horse.stats));
 		}
 
+		let arena = assets::image(&self.foreground);
+		let geometry = self.walls.geometry(&arena).map(Arc::new);
+		let field = match &geometry {
+			Some(geometry) => {
+				DistanceField::new(&geometry.image(arena.width, arena.height), self.carrots.pos)
+			}
+			None => DistanceField::new(&arena, self.carrots.pos),
+		};
+		let sim = self.sim(arena, horses, seed, geometry).with_field(field);
+		let dressing = self
+			.objects
+			.into_iter()
+			.map(|data| (assets::texture(&data.sprite), data.sound))
+			.collect();
+
 		let mut race = Race::new(
-			foreground,
-			background,
-			horses,
+			sim,
+			assets::texture(&self.background),
+			sprites,
 			self.gate,
-			self.countdown,
-			self.carrots,
-		)
-		.await;
+			&self.countdown,
+			self.carrots.carrots(),
+			entrants,
+		);
 		race.skip_intro(self.skip_intro.unwrap_or(false));
+		race.dress(dressing);
+		race.voice(sounds);
+		race.ambience(self.music.into_iter().chain(self.crowd).collect());
 		race
 	}
 }
 
+impl SeasonData {
+	pub fn load(path: impl AsRef<Path> + Debug) -> Self {
+		read(path)
+	}
+
+	pub fn into_season(self, args: RaceArgs, roster: PathBuf) -> Season {
+		Season::new(self.races, self.points, args, roster)
+	}
+
+	fn default_points() -> Vec<u32> {
+		vec![10, 6, 4, 3, 2, 1]
+	}
+}
+
 impl HorseData {
-	pub fn into_horse(self, pos: Vec2) -> Horse {
-		Horse::new(pos, self.sprite, self.win_data)
+	pub fn sprite(&self) -> Sprite {
+		Sprite::new(assets::texture(&self.sprite), self.animation, self.facing)
+	}
+}
+
+impl SoundData {
+	/// Fills in every sound left out with the one from `defaults`.
+	pub fn or(self, defaults: &Self) -> Self {
+		Self {
+			bounce: self.bounce.or_else(|| defaults.bounce.clone()),
+			hit: self.hit.or_else(|| defaults.hit.clone()),
+			victory: self.victory.or_else(|| defaults.victory.clone()),
+		}
+	}
+
+	pub fn bounce(&self) -> &Path {
+		self.bounce.as_deref().unwrap_or(Path::new(BOUNCE_SOUND))
+	}
+
+	pub fn hit(&self) -> &Path {
+		self.hit.as_deref().unwrap_or_else(|| self.bounce())
+	}
+
+	pub fn victory(&self) -> Option<&Path> {
+		self.victory.as_deref()
+	}
+
+	/// Every sound that is set, and the key it is set with.
+	fn paths(&self) -> impl Iterator<Item = (&'static str, &Path)> {
+		[
+			("bounce", &self.bounce),
+			("hit", &self.hit),
+			("victory", &self.victory),
+		]
+		.into_iter()
+		.filter_map(|(key, path)| Some((key, path.as_deref()?)))
 	}
 }
 
@@ -109,50 +533,136 @@ impl GateData {
 		(self.start, self.end - self.start)
 	}
 
-	pub async fn texture(&self) -> Texture2D {
-		Texture2D::from_image(&load_img(self.sprite.clone()).await)
+	pub fn texture(&self) -> Texture2D {
+		assets::texture(&self.sprite)
 	}
 }
 
 impl CountdownData {
-	pub fn play_sound(&self) -> StreamHandle {
+	pub fn play_sound(&self) -> Option<StreamHandle> {
 		stream(&self.audio)
 	}
 
-	pub async fn image(&self) -> Image {
-		load_img(self.sprite.clone()).await
+	pub fn texture(&self) -> Texture2D {
+		assets::texture(&self.sprite)
 	}
 }
 
 impl CarrotData {
-	pub fn into_carrots(self) -> Carrots {
-		Carrots::new(self.pos, &load_img_blocking(self.sprite))
+	pub fn carrots(&self) -> Carrots {
+		Carrots::new(assets::texture(&self.sprite))
+	}
+}
+
+impl Default for Stats {
+	fn default() -> Self {
+		Self {
+			top_speed: 3.5,
+			acceleration: 0.01,
+			elasticity: 1.,
+			size: DIR_WIDTH,
+			luck: 0.,
+		}
 	}
 }
 
 impl WinData {
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	#[cfg(test)]
+	pub fn named(name: &str) -> Self {
+		Self {
+			name: name.into(),
+			..Default::default()
+		}
+	}
+
 	pub fn into_victory(self) -> Victory {
-		Victory::new(self.name, self.screen, self.music)
+		Victory::new(self.name, assets::texture(&self.screen), self.music)
 	}
 }
 
-fn read<Out>(path: impl AsRef<Path> + Debug) -> Out
+/// Reads a data file, falling back to the default if it fails.
+pub fn read<Out>(path: impl AsRef<Path> + Debug) -> Out
 where
 	Out: DeserializeOwned + Default,
 {
-	let str = match std::fs::read_to_string(&path) {
-		Ok(ok) => ok,
-		Err(e) => {
-			tracing::error!("Failed to read file {path:?}: {e}");
-			return Out::default();
-		}
-	};
+	try_read(path).unwrap_or_else(|e| {
+		warn(&e);
+		Out::default()
+	})
+}
 
-	match toml::from_str(&str) {
-		Ok(ok) => ok,
-		Err(e) => {
-			tracing::error!("Failed to decode file {path:?}: {e}");
-			Out::default()
-		}
+/// Reads a data file, or returns why it could not be read.
+pub fn try_read<Out>(path: impl AsRef<Path>) -> Result<Out>
+where
+	Out: DeserializeOwned,
+{
+	let path = path.as_ref();
+	let str = std::fs::read_to_string(path).map_err(|e| Error::Read(path.into(), e))?;
+
+	toml::from_str(&str).map_err(|e| Error::Decode(path.into(), e))
+}
+
+/// Lets sections that are left out of a race file also be left out when it is saved.
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+	*value == T::default()
+}
+
+/// Writes a data file, replacing it if it already exists.
+pub fn write<In>(path: impl AsRef<Path>, data: &In) -> Result<()>
+where
+	In: Serialize,
+{
+	let path = path.as_ref();
+	let str = toml::to_string_pretty(data).map_err(|e| Error::Encode(path.into(), e))?;
+
+	std::fs::write(path, str).map_err(|e| Error::Write(path.into(), e))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn write_then_read() {
+		let path = std::env::temp_dir().join("openhrt_write_then_read.toml");
+		let mut data = RaceData {
+			horses: vec![(vec2(1., 2.), "horse.toml".into())],
+			..Default::default()
+		};
+		*data.point_mut(Point::Horse(0)).unwrap() = vec2(3., 4.);
+		*data.point_mut(Point::GateEnd).unwrap() = vec2(5., 6.);
+		assert!(data.point_mut(Point::Horse(1)).is_none());
+
+		write(&path, &data).unwrap();
+		let read = try_read::<RaceData>(&path).unwrap();
+
+		assert_eq!(read.points(), data.points());
+		assert_eq!(read.point(Point::Horse(0)), Some(vec2(3., 4.)));
+	}
+
+	#[test]
+	fn sounds() {
+		let horse: SoundData = toml::from_str("bounce = \"boing.ogg\"").unwrap();
+		let race: SoundData = toml::from_str("hit = \"bonk.ogg\"\nvictory = \"yay.ogg\"").unwrap();
+
+		let sounds = horse.or(&race);
+		assert_eq!(sounds.bounce(), Path::new("boing.ogg"));
+		assert_eq!(sounds.hit(), Path::new("bonk.ogg"));
+		assert_eq!(sounds.victory(), Some(Path::new("yay.ogg")));
+		// Horses without a hit sound use their bounce sound, and the default if they have neither
+		assert_eq!(
+			SoundData::default().or(&race).bounce(),
+			Path::new(BOUNCE_SOUND)
+		);
+		assert_eq!(
+			toml::from_str::<SoundData>("bounce = \"boing.ogg\"")
+				.unwrap()
+				.hit(),
+			Path::new("boing.ogg")
+		);
 	}
 }
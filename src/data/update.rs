@@ -0,0 +1,125 @@
+use std::path::Path;
+
+use serde::Serialize;
+use toml_edit::{DocumentMut, Item, TableLike, Value};
+
+use super::write;
+use crate::error::{Error, Result};
+
+/// Writes a data file, only changing the values that are different from what is already in it.
+/// Keeps the comments and formatting of hand-written files. Files that cannot be read are written from scratch.
+pub fn update<In>(path: impl AsRef<Path>, data: &In) -> Result<()>
+where
+	In: Serialize,
+{
+	let path = path.as_ref();
+	let Some(mut doc) = std::fs::read_to_string(path)
+		.ok()
+		.and_then(|str| str.parse::<DocumentMut>().ok())
+	else {
+		return write(path, data);
+	};
+
+	let str = toml::to_string(data).map_err(|e| Error::Encode(path.into(), e))?;
+	let Ok(new) = str.parse::<DocumentMut>() else {
+		return write(path, data);
+	};
+	merge_table(doc.as_table_mut(), new.as_table());
+
+	std::fs::write(path, doc.to_string()).map_err(|e| Error::Write(path.into(), e))
+}
+
+/// Adds anything missing from `old`, and recurses into everything they both have.
+/// Keys only `old` has are left alone.
+fn merge_table(old: &mut dyn TableLike, new: &dyn TableLike) {
+	for (key, new) in new.iter() {
+		match old.get_mut(key) {
+			Some(old) => merge(old, new),
+			None => {
+				old.insert(key, new.clone());
+			}
+		}
+	}
+}
+
+fn merge(old: &mut Item, new: &Item) {
+	if let (Some(old), Some(new)) = (old.as_table_like_mut(), new.as_table_like()) {
+		merge_table(old, new);
+		return;
+	}
+
+	match (old, new) {
+		(Item::ArrayOfTables(old), Item::ArrayOfTables(new)) if old.len() == new.len() => {
+			for (old, new) in old.iter_mut().zip(new.iter()) {
+				merge_table(old, new);
+			}
+		}
+		(Item::Value(old), Item::Value(new)) => merge_value(old, new),
+		(old, new) => *old = new.clone(),
+	}
+}
+
+fn merge_value(old: &mut Value, new: &Value) {
+	if let (Some(old), Some(new)) = (old.as_array_mut(), new.as_array())
+		&& old.len() == new.len()
+	{
+		for (old, new) in old.iter_mut().zip(new.iter()) {
+			merge_value(old, new);
+		}
+		return;
+	}
+	if let (Some(old), Some(new)) = (old.as_inline_table_mut(), new.as_inline_table()) {
+		merge_table(old, new);
+		return;
+	}
+	if same(old, new) {
+		return;
+	}
+
+	// Whole numbers stay integers if they were written as one
+	let mut value = match (&*old, new) {
+		(Value::Integer(_), Value::Float(float)) if float.value().fract() == 0. => {
+			Value::from(*float.value() as i64)
+		}
+		_ => new.clone(),
+	};
+	*value.decor_mut() = old.decor().clone();
+	*old = value;
+}
+
+/// If two values are exactly equal, counting integers as the same as floats.
+fn same(old: &Value, new: &Value) -> bool {
+	match (old, new) {
+		(Value::Integer(a), Value::Float(b)) => {
+			(*a.value() as f64).to_bits() == b.value().to_bits()
+		}
+		(Value::Float(a), Value::Float(b)) => a.value().to_bits() == b.value().to_bits(),
+		(Value::Integer(a), Value::Integer(b)) => a.value() == b.value(),
+		(Value::String(a), Value::String(b)) => a.value() == b.value(),
+		(Value::Boolean(a), Value::Boolean(b)) => a.value() == b.value(),
+		_ => false,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::data::{Point, RaceData};
+	use macroquad::prelude::*;
+
+	#[test]
+	fn keeps_formatting() {
+		let path = std::env::temp_dir().join("openhrt_update.toml");
+		let str = "# The race\nforeground = \"a.png\"\nbackground = \"b.png\"\nhorses = [\n\t[[10, 20], \"horse.toml\"], # fast\n\t[[30, 40], \"horse.toml\"],\n]\n\n[countdown]\naudio = \"c.ogg\"\nsprite = \"c.png\"\n\n[gate]\nstart = [0, 0]\nend = [10, 10]\nsprite = \"g.png\"\n\n[carrots]\npos = [50, 60]\nsprite = \"carrots.png\"\n";
+		std::fs::write(&path, str).unwrap();
+
+		let mut data = crate::data::read::<RaceData>(&path);
+		*data.point_mut(Point::Horse(1)).unwrap() = vec2(35., 45.5);
+		update(&path, &data).unwrap();
+		let updated = std::fs::read_to_string(&path).unwrap();
+
+		assert!(updated.starts_with("# The race\nforeground = \"a.png\"\nbackground = \"b.png\"\nhorses = [\n\t[[10, 20], \"horse.toml\"], # fast\n\t[[35, 45.5], \"horse.toml\"],\n]\n"));
+		assert!(updated.ends_with("[carrots]\npos = [50, 60]\nsprite = \"carrots.png\"\n"));
+		assert_eq!(crate::data::read::<RaceData>(&path).points(), data.points());
+	}
+}
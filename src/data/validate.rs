@@ -0,0 +1,427 @@
+use std::{
+	fmt::{self, Display},
+	path::{Path, PathBuf},
+};
+
+use macroquad::prelude::*;
+use serde::de::DeserializeOwned;
+use toml_edit::ImDocument;
+
+use super::{HorseData, RaceData, SoundData, Stats};
+use crate::{
+	audio,
+	race::sprite::Animation,
+	sim::{
+		bounce::Bounce,
+		field::DistanceField,
+		goal::{Goal, Hazard},
+		horse::is_wall,
+		object::Effect,
+	},
+	utils::try_load_img,
+};
+
+/// Every problem found while checking a race file, and the files it references.
+#[derive(Default)]
+pub struct Report {
+	problems: Vec<Problem>,
+}
+
+struct Problem {
+	file: PathBuf,
+	/// The line and column the problem is on, starting at 1
+	location: Option<(usize, usize)>,
+	message: String,
+}
+
+/// A parsed TOML file, kept around to find where values came from.
+struct Source {
+	path: PathBuf,
+	doc: Option<ImDocument<String>>,
+	text: String,
+}
+
+impl Report {
+	/// Checks a race file, every horse file it references, and every asset they use.
+	pub fn validate(path: impl AsRef<Path>) -> Self {
+		let mut report = Self::default();
+		let Some((source, race)) = report.parse::<RaceData>(path.as_ref()) else {
+			return report;
+		};
+
+		// Positions are checked against whatever the horses actually bounce off of
+		let arena = report
+			.image(&source, "foreground", &race.foreground)
+			.map(|arena| race.wall_image(&arena));
+		report.image(&source, "background", &race.background);
+		report.image(&source, "countdown.sprite", &race.countdown.sprite);
+		report.audio(&source, "countdown.audio", &race.countdown.audio);
+		report.image(&source, "gate.sprite", &race.gate.sprite);
+		report.image(&source, "carrots.sprite", &race.carrots.sprite);
+
+		if let Some(arena) = &arena {
+			// Gates are usually set into walls, so only check that they are in the arena
+			report.inside(&source, "gate.start", arena, race.gate.start);
+			report.inside(&source, "gate.end", arena, race.gate.end);
+			report.place(&source, "carrots.pos", arena, race.carrots.pos);
+		}
+		report.goal(&source, arena.as_ref(), &race.goal);
+		report.sounds(&source, &race.sounds);
+		if let Some(music) = &race.music {
+			report.audio(&source, "music", music);
+		}
+		if let Some(crowd) = &race.crowd {
+			report.audio(&source, "crowd", crowd);
+		}
+		if let Bounce::Reflect { restitution, chaos } = race.bounce {
+			report.range(&source, "bounce.restitution", restitution, 0., 1.);
+			report.range(&source, "bounce.chaos", chaos, 0., f32::INFINITY);
+		}
+
+		for (i, data) in race.objects.iter().enumerate() {
+			let key = |field: &str| format!("objects.{i}.{field}");
+			report.image(&source, &key("sprite"), &data.sprite);
+			if let Some(sound) = &data.sound {
+				report.audio(&source, &key("sound"), sound);
+			}
+
+			let object = &data.object;
+			if object.radius <= 0. {
+				report.push(
+					&source,
+					&key("radius"),
+					format!("{} should be more than 0", object.radius),
+				);
+			}
+			if let Some(arena) = &arena {
+				report.inside(&source, &key("pos"), arena, object.pos);
+				for (j, pos) in object.path.iter().enumerate() {
+					report.inside(&source, &key(&format!("path.{j}")), arena, *pos);
+				}
+				if let Effect::Teleporter { to } = object.effect {
+					report.place(&source, &key("to"), arena, to);
+				}
+			}
+		}
+
+		let field = arena
+			.as_ref()
+			.filter(|_| race.goal.uses_carrots())
+			.map(|arena| DistanceField::new(arena, race.carrots.pos));
+
+		for (i, (pos, path)) in race.horses.iter().enumerate() {
+			if let Some(arena) = &arena {
+				let key = format!("horses.{i}.0");
+				report.place(&source, &key, arena, *pos);
+
+				if field
+					.as_ref()
+					.is_some_and(|field| field.distance(*pos).is_none())
+				{
+					report.push(
+						&source,
+						&key,
+						format!("The carrots cannot be reached from horse {i}"),
+					);
+				}
+			}
+
+			if !path.exists() {
+				report.push(
+					&source,
+					&format!("horses.{i}.1"),
+					format!("Horse file \"{}\" does not exist", path.display()),
+				);
+				continue;
+			}
+
+			let Some((horse_source, horse)) = report.parse::<HorseData>(path) else {
+				continue;
+			};
+			let sprite = report.image(&horse_source, "sprite", &horse.sprite);
+			if let Some(animation) = &horse.animation {
+				report.animation(&horse_source, sprite.as_ref(), animation);
+			}
+			report.image(&horse_source, "win_data.screen", &horse.win_data.screen);
+			report.audio(&horse_source, "win_data.music", &horse.win_data.music);
+			report.stats(&horse_source, &horse.stats);
+			report.sounds(&horse_source, &horse.sounds);
+		}
+
+		report
+	}
+
+	pub fn is_ok(&self) -> bool {
+		self.problems.is_empty()
+	}
+
+	fn push(&mut self, source: &Source, key: &str, message: String) {
+		self.problems.push(Problem {
+			file: source.path.clone(),
+			location: source.locate(key),
+			message,
+		});
+	}
+
+	fn parse<Out: DeserializeOwned>(&mut self, path: &Path) -> Option<(Source, Out)> {
+		let text = match std::fs::read_to_string(path) {
+			Ok(ok) => ok,
+			Err(e) => {
+				self.problems.push(Problem {
+					file: path.into(),
+					location: None,
+					message: format!("Failed to read file: {e}"),
+				});
+				return None;
+			}
+		};
+
+		let mut source = Source {
+			path: path.into(),
+			doc: None,
+			text,
+		};
+
+		match toml::from_str(&source.text) {
+			Ok(ok) => {
+				source.doc = ImDocument::parse(source.text.clone()).ok();
+				Some((source, ok))
+			}
+			Err(e) => {
+				self.problems.push(Problem {
+					location: e.span().map(|span| source.line_col(span.start)),
+					file: source.path,
+					message: e.message().into(),
+				});
+				None
+			}
+		}
+	}
+
+	fn image(&mut self, source: &Source, key: &str, path: &Path) -> Option<Image> {
+		try_load_img(path)
+			.map_err(|e| self.push(source, key, e.to_string()))
+			.ok()
+	}
+
+	fn audio(&mut self, source: &Source, key: &str, path: &Path) {
+		if let Err(e) = audio::read(path) {
+			self.push(source, key, e.to_string());
+		}
+	}
+
+	fn sounds(&mut self, source: &Source, sounds: &SoundData) {
+		for (key, path) in sounds.paths() {
+			self.audio(source, &format!("sounds.{key}"), path);
+		}
+	}
+
+	/// Checks that a horse's stats will not break the simulation.
+	fn stats(&mut self, source: &Source, stats: &Stats) {
+		let checks = [
+			("stats.top_speed", stats.top_speed, 0., f32::INFINITY),
+			("stats.acceleration", stats.acceleration, 0., f32::INFINITY),
+			("stats.elasticity", stats.elasticity, 0., f32::INFINITY),
+			("stats.size", stats.size, 0., f32::INFINITY),
+			("stats.luck", stats.luck, 0., 1.),
+		];
+
+		for (key, value, min, max) in checks {
+			self.range(source, key, value, min, max);
+		}
+	}
+
+	/// Checks that a number is between `min` and `max`, which may be infinite.
+	fn range(&mut self, source: &Source, key: &str, value: f32, min: f32, max: f32) {
+		if !(min..=max).contains(&value) {
+			let range = if max.is_finite() {
+				format!("between {min} and {max}")
+			} else {
+				format!("at least {min}")
+			};
+			self.push(source, key, format!("{value} should be {range}"));
+		}
+	}
+
+	/// Checks that every point of the goal can be reached, and that it can be won at all.
+	fn goal(&mut self, source: &Source, arena: Option<&Image>, goal: &Goal) {
+		if let Goal::Laps { points, laps } = goal {
+			if *laps == 0 {
+				self.push(source, "goal.laps", "0 should be at least 1".into());
+			}
+			if points.is_empty() {
+				self.push(
+					source,
+					"goal.points",
+					"Laps need at least one checkpoint".into(),
+				);
+			}
+		}
+
+		if let Goal::Survival { hazards } = goal {
+			if hazards.is_empty() {
+				self.push(
+					source,
+					"goal.hazards",
+					"Survival needs at least one hazard, or the race never ends".into(),
+				);
+			}
+			for (i, Hazard { pos, radius }) in hazards.iter().enumerate() {
+				// Like gates, hazards are often set into walls
+				if let Some(arena) = arena {
+					self.inside(source, &format!("goal.hazards.{i}.pos"), arena, *pos);
+				}
+				if *radius <= 0. {
+					self.push(
+						source,
+						&format!("goal.hazards.{i}.radius"),
+						format!("{radius} should be more than 0"),
+					);
+				}
+			}
+		} else if let Some(arena) = arena {
+			for (i, pos) in goal.points().into_iter().enumerate() {
+				self.place(source, &format!("goal.points.{i}"), arena, pos);
+			}
+		}
+	}
+
+	/// Checks that a sprite sheet has frames, and every row the animation plays.
+	fn animation(&mut self, source: &Source, sheet: Option<&Image>, animation: &Animation) {
+		if animation.frame.min_element() <= 0. {
+			self.push(
+				source,
+				"animation.frame",
+				format!("Frames should be larger than {}", animation.frame),
+			);
+			return;
+		}
+		if animation.fps <= 0. {
+			self.push(
+				source,
+				"animation.fps",
+				format!("{} should be more than 0", animation.fps),
+			);
+		}
+
+		let Some(sheet) = sheet else {
+			return;
+		};
+		let rows = (f32::from(sheet.height) / animation.frame.y).floor() as u32;
+		let played = [
+			("animation.run", Some(animation.run)),
+			("animation.bounce", animation.bounce),
+			("animation.idle", animation.idle),
+		];
+		for (key, row) in played {
+			if let Some(row) = row.filter(|row| *row >= rows) {
+				self.push(
+					source,
+					key,
+					format!("Row {row} is past the end of the sheet, which has {rows}"),
+				);
+			}
+		}
+	}
+
+	/// Checks that a position is inside the arena, and not inside a wall.
+	fn place(&mut self, source: &Source, key: &str, arena: &Image, pos: Vec2) {
+		if self.inside(source, key, arena, pos) && is_wall(arena, pos) {
+			self.push(source, key, format!("Position {pos} is inside a wall"));
+		}
+	}
+
+	/// Checks that a position is inside the arena.
+	fn inside(&mut self, source: &Source, key: &str, arena: &Image, pos: Vec2) -> bool {
+		let size = vec2(arena.width() as f32, arena.height() as f32);
+		let inside = pos.x >= 0. && pos.y >= 0. && pos.x < size.x && pos.y < size.y;
+
+		if !inside {
+			self.push(
+				source,
+				key,
+				format!("Position {pos} is outside of the arena, which is {size} pixels"),
+			);
+		}
+		inside
+	}
+}
+
+impl Display for Report {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for problem in &self.problems {
+			writeln!(f, "{problem}")?;
+		}
+
+		match self.problems.len() {
+			0 => write!(f, "No problems found"),
+			1 => write!(f, "1 problem found"),
+			len => write!(f, "{len} problems found"),
+		}
+	}
+}
+
+impl Display for Problem {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.file.display())?;
+		if let Some((line, col)) = self.location {
+			write!(f, ":{line}:{col}")?;
+		}
+		write!(f, ": {}", self.message.trim_end())
+	}
+}
+
+impl Source {
+	/// Finds the line and column of the value at a dotted key, such as `horses.2.0`.
+	fn locate(&self, key: &str) -> Option<(usize, usize)> {
+		let mut item = self.doc.as_ref()?.as_item();
+		for part in key.split('.') {
+			item = match part.parse::<usize>() {
+				Ok(i) => item.get(i)?,
+				Err(_) => item.get(part)?,
+			};
+		}
+
+		item.span().map(|span| self.line_col(span.start))
+	}
+
+	fn line_col(&self, offset: usize) -> (usize, usize) {
+		let before = &self.text[..offset.min(self.text.len())];
+		let line = before.matches('\n').count() + 1;
+		let col = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+
+		(line, col)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn locate() {
+		let text = "foreground = \"a.png\"\n\nhorses = [\n\t[ [1, 2], \"b.toml\" ],\n]\n";
+		let source = Source {
+			path: PathBuf::new(),
+			doc: ImDocument::parse(text.to_string()).ok(),
+			text: text.into(),
+		};
+
+		assert_eq!(source.locate("foreground"), Some((1, 14)));
+		assert_eq!(source.locate("horses.0.1"), Some((4, 12)));
+		assert_eq!(source.locate("horses.1"), None);
+	}
+
+	#[test]
+	fn no_hazards() {
+		let source = Source {
+			path: PathBuf::new(),
+			doc: None,
+			text: String::new(),
+		};
+		let mut report = Report::default();
+		report.goal(&source, None, &Goal::Survival { hazards: vec![] });
+
+		assert_eq!(report.problems.len(), 1);
+	}
+}
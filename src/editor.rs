@@ -0,0 +1,236 @@
+use std::path::PathBuf;
+
+use macroquad::prelude::*;
+
+use crate::{
+	Scene,
+	data::{self, Point, RaceData},
+	error::warn,
+	race::sprite::Sprite,
+	sim::horse::is_wall,
+	utils::render_texture_fullscreen,
+};
+
+/// How walls are tinted when they are shown.
+const WALL_COLOR: Color = Color::new(1., 0., 0., 0.4);
+
+/// Moves the horses, gate and carrots of a race around with the mouse.
+pub struct Editor {
+	path: PathBuf,
+	data: RaceData,
+	arena: Image,
+	foreground: Texture2D,
+	background: Texture2D,
+	/// Every pixel horses bounce off of
+	walls: Texture2D,
+	sprites: Vec<Sprite>,
+	gate: Texture2D,
+	carrots: Texture2D,
+	dragging: Option<Point>,
+	show_walls: bool,
+	/// If there are changes that have not been saved
+	changed: bool,
+}
+
+impl Editor {
+	pub fn new(
+		path: PathBuf,
+		arena: Image,
+		background: Texture2D,
+		sprites: Vec<Sprite>,
+		gate: Texture2D,
+		carrots: Texture2D,
+		data: RaceData,
+	) -> Self {
+		Self {
+			path,
+			foreground: Texture2D::from_image(&arena),
+			walls: Texture2D::from_image(&walls(&data.wall_image(&arena))),
+			data,
+			arena,
+			background,
+			sprites,
+			gate,
+			carrots,
+			dragging: None,
+			show_walls: true,
+			changed: false,
+		}
+	}
+
+	fn save(&mut self) {
+		match data::update(&self.path, &self.data) {
+			Ok(()) => {
+				tracing::info!("Race saved to \"{}\"", self.path.display());
+				self.changed = false;
+			}
+			Err(e) => warn(&e),
+		}
+	}
+
+	/// The closest point under the mouse, if any.
+	fn hovered(&self) -> Option<Point> {
+		let mouse = Vec2::from(mouse_position());
+
+		self.data
+			.points()
+			.into_iter()
+			.map(|(point, pos)| (point, self.to_screen(pos).distance(mouse)))
+			.filter(|(_, distance)| *distance <= sprite_size() / 2.)
+			.min_by(|(_, a), (_, b)| a.total_cmp(b))
+			.map(|(point, _)| point)
+	}
+
+	fn arena_size(&self) -> Vec2 {
+		vec2(self.arena.width() as f32, self.arena.height() as f32)
+	}
+
+	fn to_screen(&self, pos: Vec2) -> Vec2 {
+		pos / self.arena_size() * vec2(screen_width(), screen_height())
+	}
+
+	fn to_arena(&self, pos: Vec2) -> Vec2 {
+		pos / vec2(screen_width(), screen_height()) * self.arena_size()
+	}
+
+	fn draw_gate(&self) {
+		let (Some(start), Some(end)) = (
+			self.data.point(Point::GateStart),
+			self.data.point(Point::GateEnd),
+		) else {
+			return;
+		};
+
+		let start = self.to_screen(start);
+		let size = self.to_screen(end) - start;
+		draw_texture_ex(
+			&self.gate,
+			start.x,
+			start.y,
+			WHITE,
+			DrawTextureParams {
+				dest_size: Some(size),
+				..Default::default()
+			},
+		);
+		draw_rectangle_lines(start.x, start.y, size.x, size.y, 2., YELLOW);
+	}
+
+	fn draw_point(&self, point: Point, pos: Vec2, hovered: bool) {
+		let pos = self.to_screen(pos);
+		let size = sprite_size();
+
+		let texture = match point {
+			Point::Horse(i) => self
+				.sprites
+				.get(i)
+				.map(|sprite| (&sprite.texture, sprite.first_frame())),
+			Point::Carrots => Some((&self.carrots, None)),
+			Point::GateStart | Point::GateEnd | Point::Goal(_) | Point::Object(_) => None,
+		};
+		if let Some((texture, source)) = texture {
+			draw_texture_ex(
+				texture,
+				pos.x - size / 2.,
+				pos.y - size / 2.,
+				WHITE,
+				DrawTextureParams {
+					dest_size: Some(vec2(size, size)),
+					source,
+					..Default::default()
+				},
+			);
+		}
+
+		let color = if hovered { YELLOW } else { WHITE };
+		draw_circle_lines(pos.x, pos.y, size / 2., 2., color);
+	}
+
+	fn draw_help(&self) {
+		let size = (screen_width() + screen_height()) / 90.;
+		let mouse = self.to_arena(Vec2::from(mouse_position())).round();
+		let changed = if self.changed { " (unsaved)" } else { "" };
+
+		draw_text(
+			&format!(
+				"{}{changed}  [{}, {}]  drag to move, W: walls, S: save",
+				self.path.display(),
+				mouse.x,
+				mouse.y
+			),
+			size,
+			screen_height() - size,
+			size,
+			WHITE,
+		);
+	}
+}
+
+impl Scene for Editor {
+	fn update(&mut self) {
+		if is_key_pressed(KeyCode::W) {
+			self.show_walls = !self.show_walls;
+		}
+		if is_key_pressed(KeyCode::S) {
+			self.save();
+		}
+
+		if is_mouse_button_pressed(MouseButton::Left) {
+			self.dragging = self.hovered();
+		}
+		if is_mouse_button_released(MouseButton::Left) {
+			self.dragging = None;
+		}
+
+		if let Some(point) = self.dragging {
+			let mouse = self.to_arena(Vec2::from(mouse_position()));
+			let pos = mouse.clamp(Vec2::ZERO, self.arena_size() - 1.).round();
+
+			if let Some(point) = self.data.point_mut(point)
+				&& *point != pos
+			{
+				*point = pos;
+				self.changed = true;
+			}
+		}
+	}
+
+	fn draw(&self) {
+		render_texture_fullscreen(&self.background);
+		render_texture_fullscreen(&self.foreground);
+		if self.show_walls {
+			render_texture_fullscreen(&self.walls);
+		}
+
+		self.draw_gate();
+
+		let hovered = self.dragging.or_else(|| self.hovered());
+		for (point, pos) in self.data.points() {
+			self.draw_point(point, pos, Some(point) == hovered);
+		}
+
+		self.draw_help();
+	}
+
+	fn should_finish(&self) -> bool {
+		false
+	}
+}
+
+/// The size horses and carrots are drawn at, the same as in a race.
+fn sprite_size() -> f32 {
+	(screen_width() + screen_height()) / 40.
+}
+
+/// Tints every pixel of the arena that horses collide with.
+fn walls(arena: &Image) -> Image {
+	let mut walls = Image::gen_image_color(arena.width, arena.height, BLANK);
+	for y in 0..arena.height as u32 {
+		for x in 0..arena.width as u32 {
+			if is_wall(arena, vec2(x as f32, y as f32)) {
+				walls.set_pixel(x, y, WALL_COLOR);
+			}
+		}
+	}
+	walls
+}
@@ -0,0 +1,117 @@
+use std::{
+	fmt::{self, Display},
+	path::PathBuf,
+	sync::LazyLock,
+	time::{Duration, Instant},
+};
+
+use kira::{PlaySoundError, sound::FromFileError};
+use macroquad::prelude::*;
+use parking_lot::Mutex;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// How long a warning stays on screen.
+const WARNING_TIME: Duration = Duration::from_secs(10);
+/// The most warnings kept at once. Older ones are dropped first.
+const MAX_WARNINGS: usize = 8;
+
+static WARNINGS: LazyLock<Mutex<Vec<Warning>>> = LazyLock::new(|| Mutex::new(vec![]));
+
+#[derive(Debug)]
+pub enum Error {
+	/// A file could not be read
+	Read(PathBuf, std::io::Error),
+	/// A file could not be written
+	Write(PathBuf, std::io::Error),
+	/// A data file could not be decoded
+	Decode(PathBuf, toml::de::Error),
+	/// A data file could not be encoded
+	Encode(PathBuf, toml::ser::Error),
+	/// An image could not be decoded
+	Image(PathBuf, macroquad::Error),
+	/// An audio file could not be decoded
+	Audio(PathBuf, FromFileError),
+	/// The audio device could not be opened
+	AudioManager(kira::backend::cpal::Error),
+	/// Too many sounds were already playing
+	SoundLimit(PathBuf),
+	/// The races simulated for the odds could not be finished
+	Odds(tokio::task::JoinError),
+}
+
+struct Warning {
+	message: String,
+	time: Instant,
+}
+
+impl Error {
+	/// Converts an error from playing a sound.
+	pub fn play(path: impl Into<PathBuf>, e: PlaySoundError<FromFileError>) -> Self {
+		match e {
+			PlaySoundError::SoundLimitReached => Self::SoundLimit(path.into()),
+			PlaySoundError::IntoSoundError(e) => Self::Audio(path.into(), e),
+		}
+	}
+}
+
+impl Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Read(path, e) => write!(f, "Failed to read file \"{}\": {e}", path.display()),
+			Self::Write(path, e) => write!(f, "Failed to write file \"{}\": {e}", path.display()),
+			Self::Decode(path, e) => {
+				write!(f, "Failed to decode file \"{}\": {e}", path.display())
+			}
+			Self::Encode(path, e) => {
+				write!(f, "Failed to encode file \"{}\": {e}", path.display())
+			}
+			Self::Image(path, e) => write!(f, "Image \"{}\" failed to load: {e}", path.display()),
+			Self::Audio(path, e) => write!(f, "Audio \"{}\" failed to load: {e}", path.display()),
+			Self::AudioManager(e) => write!(f, "Could not create audio manager: {e}"),
+			Self::SoundLimit(path) => write!(
+				f,
+				"Too many sounds are playing to play \"{}\"",
+				path.display()
+			),
+			Self::Odds(e) => write!(f, "Could not work out the odds, so they are even: {e}"),
+		}
+	}
+}
+
+impl std::error::Error for Error {}
+
+/// Logs an error, and shows it on screen for a while.
+/// Used when the game can carry on without whatever failed.
+pub fn warn(error: &Error) {
+	tracing::warn!("{error}");
+
+	let mut warnings = WARNINGS.lock();
+	if warnings.len() >= MAX_WARNINGS {
+		warnings.remove(0);
+	}
+	warnings.push(Warning {
+		message: error.to_string(),
+		time: Instant::now(),
+	});
+}
+
+/// Draws recent warnings over everything else, fading them out as they expire.
+pub fn draw_warnings() {
+	let mut warnings = WARNINGS.lock();
+	warnings.retain(|warning| warning.time.elapsed() < WARNING_TIME);
+
+	let size = (screen_width() + screen_height()) / 100.;
+	for (i, warning) in warnings.iter().enumerate() {
+		let left = 1. - warning.time.elapsed().as_secs_f32() / WARNING_TIME.as_secs_f32();
+		let y = size * 1.5 * (i + 1) as f32;
+
+		draw_text(
+			&warning.message,
+			size,
+			y,
+			size,
+			Color::new(1., 0.3, 0.3, left.clamp(0., 1.)),
+		);
+	}
+}
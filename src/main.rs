@@ -5,24 +5,80 @@
 #![allow(clippy::cast_possible_truncation)]
 #![allow(clippy::semicolon_if_nothing_returned)]
 
+use std::{
+	path::{Path, PathBuf},
+	sync::atomic::AtomicUsize,
+};
+
+use clap::Parser;
 use macroquad::{miniquad::conf::Platform, prelude::*};
 
+use betting::{Betting, Ledger, Odds};
+use cli::{Args, Mode, RaceArgs, WindowArgs};
+use data::{RaceData, SeasonData};
+use error::Error;
+use replay::{Recorder, Replay};
+use roster::Roster;
+use sim::Event;
+
+mod assets;
 mod audio;
+mod betting;
+mod cli;
 mod data;
+mod editor;
+mod error;
 mod race;
+mod replay;
+mod roster;
+mod season;
+mod sim;
 mod utils;
+mod watch;
+
+fn main() {
+	let args = Args::parse();
+	utils::init_log(args.log_level);
+
+	match args.mode {
+		Some(Mode::Simulate { race, limit }) => {
+			simulate(&race, limit, args.record, Roster::load(args.roster));
+		}
+		Some(Mode::Odds { path, races, limit }) => odds(&path, races, limit),
+		Some(Mode::Roster) => println!("{}", Roster::load(args.roster)),
+		Some(Mode::Validate { path }) => validate(&path),
+		None => macroquad::Window::from_config(conf(&args.window), run(args)),
+	}
+}
 
-#[macroquad::main(conf)]
-async fn main() {
-	utils::init_log();
+/// Something that can be run by the main loop.
+pub trait Scene {
+	fn update(&mut self);
+	fn draw(&self);
+	fn should_finish(&self) -> bool;
+}
 
-	let mut race = race();
+async fn run(args: Args) {
+	let mut scene: Box<dyn Scene> = match &args.season {
+		Some(path) => Box::new(SeasonData::load(path).into_season(args.race, args.roster)),
+		None if args.watch => Box::new(watch::Watch::new(args.race)),
+		// Fall back to nothing, rather than the default race, so the file is not overwritten with it
+		None if args.edit => match data::try_read::<RaceData>(&args.race.path) {
+			Ok(data) => Box::new(data.into_editor(args.race.path)),
+			Err(e) => {
+				tracing::error!("{e}");
+				return;
+			}
+		},
+		None => Box::new(race(args).await),
+	};
 
 	loop {
-		race.draw();
-		race.update();
+		scene.draw();
+		error::draw_warnings();
+		scene.update();
 
-		if race.should_finish() {
+		if scene.should_finish() {
 			tracing::info!("Race finished");
 			return;
 		}
@@ -31,23 +87,142 @@ async fn main() {
 	}
 }
 
-fn race() -> race::Race {
-	let runtime = tokio::runtime::Runtime::new().unwrap_or_else(|e| {
-		tracing::error!("Could not create runtime: {e}");
-		panic!()
+async fn race(args: Args) -> race::Race {
+	let replay = args.replay.map(Replay::load);
+	let data = match &replay {
+		Some(replay) => replay.race(),
+		None => RaceData::load(&args.race.path),
+	}
+	.with_overrides(&args.race)
+	.set_seed();
+	let recorder = args.record.map(|path| Recorder::new(path, &data));
+	let horses = data.horse_count();
+	// Bets are taken during the countdown, so there has to be one
+	let data = match args.bets {
+		Some(_) => data.with_intro(),
+		None => data,
+	};
+	let odds_data = args.bets.is_some().then(|| data.clone());
+
+	let mut loading = data.preload();
+	// Finding the odds simulates a lot of races, so it is done while everything else loads
+	let odds = odds_data.map(|data| {
+		let done = loading.add(Odds::SEEDS.count());
+		utils::spawn_blocking(move || data.odds(&done))
+	});
+	while !loading.is_done() || odds.as_ref().is_some_and(|odds| !odds.is_finished()) {
+		loading.draw();
+		next_frame().await;
+	}
+
+	let betting = match (args.bets, odds) {
+		(Some(path), Some(odds)) => {
+			let odds = odds.await.unwrap_or_else(|e| {
+				error::warn(&Error::Odds(e));
+				Odds::even(horses)
+			});
+			Some(Betting::new(Ledger::load(path, &args.players), odds))
+		}
+		_ => None,
+	};
+
+	let mut race = loading.finish();
+	// Replays are races that have already been run, so they are not counted again
+	match replay {
+		Some(replay) => race.play(replay),
+		None => race.track(Roster::load(args.roster)),
+	}
+	if let Some(recorder) = recorder {
+		race.record(recorder);
+	}
+	if let Some(betting) = betting {
+		race.bet(betting);
+	}
+	race
+}
+
+fn simulate(args: &RaceArgs, limit: f32, record: Option<PathBuf>, mut roster: Roster) {
+	let data = RaceData::load(&args.path).with_overrides(args).set_seed();
+	let seed = data.seed().unwrap_or_default();
+	let mut recorder = record.map(|path| Recorder::new(path, &data));
+	let names = data.horse_names();
+	let entrants = data.entrants();
+	let mut bounces = vec![0; entrants.len()];
+
+	let outcome = data.into_sim().run(limit, |sim, events| {
+		if let Some(recorder) = &mut recorder {
+			recorder.record(sim, events);
+		}
+		for event in events {
+			if let Event::Bounce(i) | Event::Hit(i) = event {
+				bounces[*i] += 1;
+			}
+		}
+		// Like races in a window, only races that end are counted
+		if sim.is_over() {
+			roster.record(&entrants, sim, &bounces);
+			roster.save();
+		}
 	});
 
-	runtime.block_on(async {
-		data::RaceData::load("./data/race.toml")
-			.set_seed()
-			.into_race()
-			.await
-	})
+	match outcome.winner {
+		Some(horse) => println!(
+			"{} won in {} (seed {seed})",
+			horse.win_data.name(),
+			race::parse_time(outcome.finished[0].time)
+		),
+		None => println!(
+			"No horse finished within {} (seed {seed})",
+			race::parse_time(outcome.time)
+		),
+	}
+
+	if outcome.finished.len() > 1 {
+		for (place, finish) in outcome.finished.iter().enumerate() {
+			println!(
+				"{}. {} in {}",
+				place + 1,
+				names[finish.horse],
+				race::parse_time(finish.time)
+			);
+		}
+	}
 }
 
-fn conf() -> Conf {
+fn odds(path: &Path, races: u64, limit: f32) {
+	let data = RaceData::load(path);
+	let names = data.horse_names();
+	let estimate = data.estimate(0..races, limit, &AtomicUsize::new(0));
+
+	for (name, horse) in names.iter().zip(&estimate.horses) {
+		let time = horse
+			.median_time
+			.map_or_else(|| "-".into(), race::parse_time);
+		println!(
+			"{name:<16} {:>6.2}% to win, median time {time}",
+			horse.chance(estimate.races) * 100.
+		);
+	}
+	println!("From {} races", estimate.races);
+}
+
+fn validate(path: &Path) {
+	let report = data::Report::validate(path);
+	println!("{report}");
+
+	if !report.is_ok() {
+		std::process::exit(1);
+	}
+}
+
+fn conf(args: &WindowArgs) -> Conf {
+	let default = Conf::default();
+
 	Conf {
 		window_title: "OpenHRT".into(),
+		window_width: args.width.unwrap_or(default.window_width),
+		window_height: args.height.unwrap_or(default.window_height),
+		fullscreen: args.fullscreen,
 		platform: Platform {
 			//swap_interval: Some(0),
 			..Default::default()
@@ -1,47 +1,152 @@
+use std::{
+	path::{Path, PathBuf},
+	sync::{Arc, atomic::AtomicUsize},
+};
+
+use kira::{Tween, sound::static_sound::StaticSoundHandle};
 use macroquad::prelude::*;
 
-use horse::{Collisions, Horse, NO_COLLISION};
+use leaderboard::Leaderboard;
+use results::Results;
+use sprite::{Facing, Pose, Sprite};
 use startup::Startup;
 use victory::{Carrots, Victory};
 
 use crate::{
-	data::{CarrotData, CountdownData, GateData},
+	Scene,
+	assets::Progress,
+	audio::{play_looped, play_or_load},
+	betting::Betting,
+	data::{CountdownData, GateData, RaceData, SoundData},
+	replay::{Recorder, Replay},
+	roster::Roster,
+	sim::{Event, Sim, TICK, goal::Goal, horse::DIR_WIDTH},
 	utils::render_texture_fullscreen,
 };
 
-pub mod horse;
+mod leaderboard;
+mod results;
+pub mod sprite;
 mod startup;
 pub mod victory;
 
+/// The most time the simulation will try to catch up on in a single frame.
+/// Prevents a long stall from freezing the game while it simulates everything it missed.
+const MAX_FRAME_TIME: f32 = TICK * 15.;
+
+pub const BOUNCE_SOUND: &str = "./assets/audio/bounce.flac";
+
+/// A race whose assets are still loading.
+pub struct Loading {
+	progress: Progress,
+	data: Box<RaceData>,
+}
+
 pub struct Race {
-	time: f32,
-	foreground: Image,
+	sim: Sim,
+	/// Frame time that has not been simulated yet
+	accumulator: f32,
+	/// Horse positions before the latest tick, used to interpolate between ticks
+	previous: Vec<Vec2>,
+	foreground: Texture2D,
 	background: Texture2D,
-	horses: Vec<Horse>,
-	carrots: Carrots,
+	sprites: Vec<Sprite>,
+	carrots: Sprite,
 	startup: Option<Startup>,
 	victory: Option<Victory>,
+	replay: Option<Replay>,
+	recorder: Option<Recorder>,
+	betting: Option<Betting>,
+	roster: Option<Roster>,
+	/// The file of every horse in the race
+	entrants: Vec<PathBuf>,
+	/// How many times each horse has bounced
+	bounces: Vec<u32>,
+	/// When each horse last bounced, in simulated seconds
+	bounced: Vec<f32>,
+	leaderboard: Leaderboard,
+	/// The sprite of every object in the arena, and the sound it makes when triggered
+	objects: Vec<(Texture2D, Option<PathBuf>)>,
+	/// The sounds of every horse
+	sounds: Vec<SoundData>,
+	/// Sounds to loop once the horses start running, taken when they start
+	ambience: Option<Vec<PathBuf>>,
+	/// The looping sounds that are playing
+	playing: Vec<StaticSoundHandle>,
+	/// Shown after the victory screen, if more than one horse reached the carrots
+	results: Option<Results>,
+	/// The distance field, drawn over the arena for debugging
+	field: Option<Texture2D>,
+	/// If the distance field, and any wall segments, are drawn over the arena
+	show_field: bool,
+}
+
+impl Loading {
+	pub fn new(progress: Progress, data: RaceData) -> Self {
+		Self {
+			progress,
+			data: Box::new(data),
+		}
+	}
+
+	/// Waits for `total` more things to finish before the race starts.
+	pub fn add(&mut self, total: usize) -> Arc<AtomicUsize> {
+		self.progress.add(total)
+	}
+
+	pub fn is_done(&self) -> bool {
+		self.progress.is_done()
+	}
+
+	pub fn draw(&self) {
+		self.progress.draw();
+	}
+
+	/// Builds the race. Anything that has not finished loading yet will be loaded now.
+	pub fn finish(self) -> Race {
+		self.data.into_race()
+	}
 }
 
 impl Race {
-	pub async fn new(
-		foreground: impl Future<Output = Image>,
-		background: impl Future<Output = Image>,
-		horses: Vec<Horse>,
+	pub fn new(
+		sim: Sim,
+		background: Texture2D,
+		sprites: Vec<Sprite>,
 		gate: GateData,
-		countdown: CountdownData,
-		carrots: CarrotData,
+		countdown: &CountdownData,
+		carrots: Carrots,
+		entrants: Vec<PathBuf>,
 	) -> Self {
-		let (foreground, background) = tokio::join!(foreground, background);
-
 		Self {
-			time: 0.,
-			foreground,
-			background: Texture2D::from_image(&background),
-			horses,
-			carrots: carrots.into_carrots(),
-			startup: Some(Startup::new(&background, gate, countdown).await),
+			accumulator: 0.,
+			previous: sim.horses().iter().map(|horse| horse.pos).collect(),
+			foreground: Texture2D::from_image(sim.arena()),
+			background,
+			sprites,
+			carrots: Sprite::new(carrots.texture, None, Facing::None),
+			startup: Some(Startup::new(sim.arena(), gate, countdown)),
 			victory: None,
+			replay: None,
+			recorder: None,
+			betting: None,
+			roster: None,
+			entrants,
+			bounces: vec![0; sim.horses().len()],
+			bounced: vec![f32::NEG_INFINITY; sim.horses().len()],
+			leaderboard: Leaderboard::new(&sim),
+			objects: vec![],
+			sounds: vec![SoundData::default(); sim.horses().len()],
+			ambience: None,
+			playing: vec![],
+			results: None,
+			field: sim.field().map(|field| {
+				let texture = Texture2D::from_image(&field.image());
+				texture.set_filter(FilterMode::Nearest);
+				texture
+			}),
+			show_field: false,
+			sim,
 		}
 	}
 
@@ -51,71 +156,325 @@ impl Race {
 		}
 	}
 
-	pub fn update(&mut self) {
+	/// Gives the objects in the arena their sprites and sounds, in the same order as the simulation has them.
+	pub fn dress(&mut self, objects: Vec<(Texture2D, Option<PathBuf>)>) {
+		self.objects = objects;
+	}
+
+	/// Gives every horse its sounds, in order of id.
+	pub fn voice(&mut self, sounds: Vec<SoundData>) {
+		self.sounds = sounds;
+	}
+
+	/// Loops the sounds in the background while the horses are running, such as music and a crowd.
+	pub fn ambience(&mut self, sounds: Vec<PathBuf>) {
+		self.ambience = Some(sounds);
+	}
+
+	/// Plays back a recorded race, instead of simulating a new one.
+	pub fn play(&mut self, replay: Replay) {
+		self.replay = Some(replay);
+	}
+
+	pub fn record(&mut self, recorder: Recorder) {
+		self.recorder = Some(recorder);
+	}
+
+	/// Takes bets during the countdown, and pays them out once a horse wins.
+	pub fn bet(&mut self, betting: Betting) {
+		self.betting = Some(betting);
+	}
+
+	/// Adds the race to the careers of every horse in it, once the race is over.
+	pub fn track(&mut self, roster: Roster) {
+		self.roster = Some(roster);
+	}
+
+	pub fn sim(&self) -> &Sim {
+		&self.sim
+	}
+
+	fn tick(&mut self) {
+		self.previous.clear();
+		self.previous
+			.extend(self.sim.horses().iter().map(|horse| horse.pos));
+
+		let events = match &self.replay {
+			Some(replay) => replay.play(&mut self.sim),
+			None => self.sim.step(),
+		};
+
+		if let Some(recorder) = &mut self.recorder {
+			recorder.record(&self.sim, &events);
+		}
+
+		for event in events {
+			match event {
+				Event::Bounce(i) | Event::Hit(i) => {
+					self.bounces[i] += 1;
+					self.bounced[i] = self.sim.time();
+					let sounds = self.sounds.get(i);
+					let sound = match event {
+						Event::Hit(_) => sounds.map_or(Path::new(BOUNCE_SOUND), SoundData::hit),
+						_ => sounds.map_or(Path::new(BOUNCE_SOUND), SoundData::bounce),
+					};
+					play_or_load(sound);
+				}
+				Event::Finish(i) => {
+					tracing::info!(
+						"{} reached the carrots in {}",
+						self.sim.horses()[i].win_data.name(),
+						parse_time(self.sim.time())
+					);
+				}
+				Event::Checkpoint(i) => {
+					tracing::debug!(
+						"{} touched checkpoint {}",
+						self.sim.horses()[i].win_data.name(),
+						self.sim.progress(i)
+					);
+				}
+				Event::Object(_, object) => {
+					if let Some((_, Some(sound))) = self.objects.get(object) {
+						play_or_load(sound);
+					}
+				}
+				Event::Out(i) => {
+					tracing::info!(
+						"{} is out after {}",
+						self.sim.horses()[i].win_data.name(),
+						parse_time(self.sim.time())
+					);
+				}
+			}
+		}
+
+		if self.sim.is_over() {
+			self.finish();
+		}
+	}
+
+	/// Settles everything that depends on the result, then shows the winner.
+	fn finish(&mut self) {
+		if let Some(roster) = &mut self.roster {
+			roster.record(&self.entrants, &self.sim, &self.bounces);
+			roster.save();
+		}
+		if let Some(betting) = &mut self.betting {
+			betting.settle(&self.sim.standings());
+		}
+
+		// A single winner says everything the results would
+		if self.sim.finished().len() > 1 {
+			self.results = Some(Results::new(&self.sim));
+		}
+		self.hush();
+		if let Some(winner) = self.sim.winner() {
+			if let Some(sting) = self.sounds.get(winner.id).and_then(SoundData::victory) {
+				play_or_load(sting);
+			}
+			self.victory = Some(winner.win_data.clone().into_victory());
+		}
+	}
+
+	/// Stops the looping sounds.
+	fn hush(&mut self) {
+		for handle in &mut self.playing {
+			handle.stop(Tween::default());
+		}
+		self.playing.clear();
+	}
+
+	/// Draws the checkpoints or hazards of the goal, if it has any.
+	fn draw_goal(&self) {
+		let scale = self.scale();
+		let font = (screen_width() + screen_height()) / 70.;
+
+		match self.sim.goal() {
+			Goal::Survival { hazards } => {
+				for hazard in hazards {
+					let pos = hazard.pos * scale;
+					let radius = hazard.radius * scale.x;
+					draw_circle(pos.x, pos.y, radius, Color::new(1., 0., 0., 0.4));
+					draw_circle_lines(pos.x, pos.y, radius, 2., RED);
+				}
+			}
+			goal => {
+				let radius = DIR_WIDTH * scale.x;
+				for (i, point) in goal.points().into_iter().enumerate() {
+					let pos = point * scale;
+					draw_circle_lines(pos.x, pos.y, radius, 3., YELLOW);
+					if !matches!(goal, Goal::Any { .. }) {
+						draw_text(&(i + 1).to_string(), pos.x, pos.y, font, YELLOW);
+					}
+				}
+			}
+		}
+	}
+
+	/// Draws the segments horses bounce off of, if the walls are made of them.
+	fn draw_geometry(&self) {
+		let Some(geometry) = self.sim.geometry() else {
+			return;
+		};
+		let scale = self.scale();
+		for [a, b] in geometry.segments() {
+			let (a, b) = (*a * scale, *b * scale);
+			draw_line(a.x, a.y, b.x, b.y, 2., MAGENTA);
+		}
+	}
+
+	/// Draws every object in the arena where it is, at its size.
+	fn draw_objects(&self) {
+		let scale = self.scale();
+		for ((object, pos), (texture, _)) in self.sim.objects().zip(&self.objects) {
+			let pos = pos * scale;
+			let size = object.radius * 2. * scale;
+			draw_texture_ex(
+				texture,
+				pos.x - size.x / 2.,
+				pos.y - size.y / 2.,
+				WHITE,
+				DrawTextureParams {
+					dest_size: Some(size),
+					..Default::default()
+				},
+			);
+		}
+	}
+
+	/// How many pixels on screen each pixel of the arena takes up.
+	fn scale(&self) -> Vec2 {
+		let arena = self.sim.arena();
+		vec2(
+			screen_width() / arena.width() as f32,
+			screen_height() / arena.height() as f32,
+		)
+	}
+
+	/// Draws a horse or the carrots, animated and facing `dir`. `scale` is relative to a horse of the default size.
+	fn little_guy(&self, pos: Vec2, sprite: &Sprite, scale: f32, dir: Vec2, pose: Pose) {
+		let size = (screen_width() + screen_height()) / 40. * scale;
+		// Animations follow the simulation, so they freeze when it does
+		let time = self.sim.time() + self.accumulator;
+		sprite.draw(pos * self.scale(), size, dir, pose, time);
+	}
+}
+
+impl Scene for Race {
+	fn update(&mut self) {
 		if is_key_down(KeyCode::Backslash) && self.victory.is_none() {
 			self.startup = None;
-			self.victory = Some(self.horses.first().unwrap().win_data.clone().into_victory());
+			self.hush();
+			self.victory = Some(self.sim.horses()[0].win_data.clone().into_victory());
+		}
+
+		if is_key_pressed(KeyCode::F) {
+			self.show_field = !self.show_field;
 		}
 
 		if let Some(startup) = &mut self.startup {
 			startup.update();
+			if let Some(betting) = &mut self.betting {
+				betting.update();
+			}
 
 			if startup.done() {
 				self.startup = None;
+				if let Some(betting) = &mut self.betting {
+					betting.lock();
+				}
 			}
 
 			return;
 		} else if let Some(victory) = &mut self.victory {
-			victory.update();
+			match &mut self.results {
+				Some(results) if victory.should_finish() => results.update(),
+				_ => victory.update(),
+			}
 			return;
 		}
 
-		self.time += get_frame_time();
-
-		let collisions = self
-			.horses
-			.iter()
-			.map(|horse| {
-				horse.collision_wall(&self.foreground) | horse.collision_honses(&self.horses)
-			})
-			.collect::<Vec<Collisions>>();
-
-		for (i, collision) in collisions.iter().enumerate() {
-			let honse = &mut self.horses[i];
-			honse.update();
-
-			if *collision != NO_COLLISION {
-				honse.bounce(*collision);
-			}
+		if let Some(ambience) = self.ambience.take() {
+			self.playing = ambience.iter().filter_map(play_looped).collect();
 		}
 
-		for horse in &self.horses {
-			if horse.collision_carrots(&self.carrots) {
-				self.victory = Some(horse.win_data.clone().into_victory())
-			}
+		self.accumulator = (self.accumulator + get_frame_time()).min(MAX_FRAME_TIME);
+		while self.accumulator >= TICK && self.victory.is_none() {
+			self.accumulator -= TICK;
+			self.tick();
 		}
+
+		self.leaderboard.update(&self.sim);
 	}
 
-	pub fn should_finish(&self) -> bool {
-		matches!(&self.victory, Some(victory) if victory.should_finish())
+	fn should_finish(&self) -> bool {
+		let victory = matches!(&self.victory, Some(victory) if victory.should_finish());
+		victory && self.results.as_ref().is_none_or(Results::is_done)
 	}
 
-	pub fn draw(&self) {
+	fn draw(&self) {
+		if let (Some(victory), Some(results)) = (&self.victory, &self.results)
+			&& victory.should_finish()
+		{
+			results.draw(&self.sprites);
+			return;
+		}
+
 		if let Some(victory) = &self.victory {
 			victory.draw();
+			if let Some(betting) = &self.betting {
+				betting.draw_results();
+			}
 			return;
 		}
 
 		render_texture_fullscreen(&self.background);
-		render_texture_fullscreen(&Texture2D::from_image(&self.foreground));
+		render_texture_fullscreen(&self.foreground);
+		if let Some(field) = &self.field
+			&& self.show_field
+		{
+			render_texture_fullscreen(field);
+			self.draw_geometry();
+		}
+
+		// How far the game is between the previous tick and the next one
+		let alpha = self.accumulator / TICK;
 
-		self.little_guy(self.carrots.pos, &self.carrots.texture);
-		for horse in &self.horses {
-			self.little_guy(horse.pos, &horse.texture);
+		self.draw_goal();
+		self.draw_objects();
+		if self.sim.goal().uses_carrots() {
+			self.little_guy(self.sim.carrots(), &self.carrots, 1., Vec2::X, Pose::Idle);
 		}
+		for ((horse, previous), sprite) in self
+			.sim
+			.horses()
+			.iter()
+			.zip(&self.previous)
+			.zip(&self.sprites)
+		{
+			if self.sim.is_out(horse.id) {
+				continue;
+			}
+			let pose = if self.startup.is_some() || !self.sim.is_running(horse.id) {
+				Pose::Idle
+			} else {
+				Pose::Run(self.sim.time() - self.bounced[horse.id])
+			};
+			let scale = horse.stats.size / DIR_WIDTH;
+			self.little_guy(
+				previous.lerp(horse.pos, alpha),
+				sprite,
+				scale,
+				horse.dir,
+				pose,
+			);
+		}
+
+		self.leaderboard.draw(&self.sim, &self.sprites);
 
 		draw_text(
-			&parse_time(self.time),
+			&parse_time(self.sim.time()),
 			screen_width() * 0.85,
 			screen_height() * 0.95,
 			(screen_width() + screen_height()) / 70.,
@@ -124,30 +483,20 @@ impl Race {
 
 		if let Some(startup) = &self.startup {
 			startup.draw();
+			if let Some(betting) = &self.betting {
+				betting.draw(self.sim.horses());
+			}
 		}
 	}
+}
 
-	fn little_guy(&self, pos: Vec2, texture: &Texture2D) {
-		let size = (screen_width() + screen_height()) / 40.;
-		let params = DrawTextureParams {
-			dest_size: Some(vec2(size, size)),
-			..Default::default()
-		};
-
-		let pos_x = pos.x / self.foreground.width() as f32 * screen_width();
-		let pos_y = pos.y / self.foreground.height() as f32 * screen_height();
-
-		draw_texture_ex(
-			texture,
-			pos_x - (size / 2.),
-			pos_y - (size / 2.),
-			WHITE,
-			params,
-		);
+impl Drop for Race {
+	fn drop(&mut self) {
+		self.hush();
 	}
 }
 
-fn parse_time(mut time: f32) -> String {
+pub fn parse_time(mut time: f32) -> String {
 	let mut minutes = 0u8;
 	while time >= 60. {
 		minutes += 1;
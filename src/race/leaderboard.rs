@@ -0,0 +1,108 @@
+use macroquad::prelude::*;
+
+use super::sprite::Sprite;
+use crate::sim::Sim;
+
+/// How long a horse stays highlighted after its rank changes, in seconds.
+const HIGHLIGHT_TIME: f32 = 1.5;
+
+/// A sidebar ranking every horse by how close it is to the carrots.
+pub struct Leaderboard {
+	/// The id of every horse, from first place to last
+	order: Vec<usize>,
+	/// The latest rank change of every horse by id, and how long ago it was
+	changes: Vec<(Change, f32)>,
+	hidden: bool,
+}
+
+#[derive(Clone, Copy)]
+enum Change {
+	Up,
+	Down,
+}
+
+impl Leaderboard {
+	pub fn new(sim: &Sim) -> Self {
+		Self {
+			order: sim.standings(),
+			changes: vec![(Change::Up, HIGHLIGHT_TIME); sim.horses().len()],
+			hidden: false,
+		}
+	}
+
+	/// Re-ranks the horses, highlighting any that moved.
+	pub fn update(&mut self, sim: &Sim) {
+		if is_key_pressed(KeyCode::L) {
+			self.hidden = !self.hidden;
+		}
+
+		let order = sim.standings();
+		for (rank, horse) in order.iter().enumerate() {
+			let Some(old) = self.order.iter().position(|i| i == horse) else {
+				continue;
+			};
+
+			if rank != old {
+				let change = if rank < old { Change::Up } else { Change::Down };
+				self.changes[*horse] = (change, 0.);
+			}
+		}
+		self.order = order;
+
+		for (_, time) in &mut self.changes {
+			*time += get_frame_time();
+		}
+	}
+
+	pub fn draw(&self, sim: &Sim, sprites: &[Sprite]) {
+		if self.hidden {
+			return;
+		}
+
+		let (width, height) = (screen_width(), screen_height());
+		let size = (width + height) / 90.;
+		let row = size * 1.6;
+		let x = width * 0.8;
+		let y = size;
+
+		draw_rectangle(
+			x - size * 0.5,
+			y - size * 0.5,
+			width - x,
+			row * self.order.len() as f32 + size,
+			Color::new(0., 0., 0., 0.5),
+		);
+
+		for (rank, horse) in self.order.iter().enumerate() {
+			let y = y + row * rank as f32;
+			let (change, time) = self.changes[*horse];
+			let highlight = (1. - time / HIGHLIGHT_TIME).clamp(0., 1.);
+			let color = match change {
+				Change::Up => Color::new(1. - highlight, 1., 1. - highlight, 1.),
+				Change::Down => Color::new(1., 1. - highlight, 1. - highlight, 1.),
+			};
+
+			draw_text(&format!("{}.", rank + 1), x, y + size, size, color);
+			if let Some(sprite) = sprites.get(*horse) {
+				draw_texture_ex(
+					&sprite.texture,
+					x + size * 1.5,
+					y,
+					WHITE,
+					DrawTextureParams {
+						dest_size: Some(vec2(size * 1.2, size * 1.2)),
+						source: sprite.first_frame(),
+						..Default::default()
+					},
+				);
+			}
+			draw_text(
+				sim.horses()[*horse].win_data.name(),
+				x + size * 3.,
+				y + size,
+				size,
+				color,
+			);
+		}
+	}
+}
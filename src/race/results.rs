@@ -0,0 +1,85 @@
+use macroquad::prelude::*;
+
+use super::{parse_time, sprite::Sprite};
+use crate::sim::Sim;
+
+/// How long the results are shown, unless skipped.
+const RESULTS_TIME: f32 = 10.;
+
+/// Every horse's place and time, shown after the victory screen.
+pub struct Results {
+	/// Every horse's id, name, and time if it reached the carrots, from first place to last
+	rows: Vec<(usize, String, Option<f32>)>,
+	time: f32,
+}
+
+impl Results {
+	pub fn new(sim: &Sim) -> Self {
+		let rows = sim
+			.standings()
+			.into_iter()
+			.map(|id| {
+				let horse = &sim.horses()[id];
+				let time = sim
+					.finished()
+					.iter()
+					.find(|finish| finish.horse == horse.id)
+					.map(|finish| finish.time);
+				(horse.id, horse.win_data.name().to_string(), time)
+			})
+			.collect();
+
+		Self { rows, time: 0. }
+	}
+
+	pub fn update(&mut self) {
+		self.time += get_frame_time();
+
+		if is_key_pressed(KeyCode::Space) || is_key_pressed(KeyCode::Enter) {
+			self.time = RESULTS_TIME;
+		}
+	}
+
+	pub fn is_done(&self) -> bool {
+		self.time >= RESULTS_TIME
+	}
+
+	/// Draws every row, with the sprite of the horse with that id next to its name.
+	pub fn draw(&self, sprites: &[Sprite]) {
+		clear_background(BLACK);
+
+		let (width, height) = (screen_width(), screen_height());
+		let size = (width + height) / 50.;
+
+		draw_text("Results", width * 0.1, height * 0.15, size * 1.5, WHITE);
+
+		for (i, (id, name, time)) in self.rows.iter().enumerate() {
+			let y = height * 0.25 + size * 1.2 * i as f32;
+			let color = match (i, time) {
+				(_, None) => GRAY,
+				(0, _) => GOLD,
+				(1, _) => LIGHTGRAY,
+				(2, _) => ORANGE,
+				_ => WHITE,
+			};
+			let time = time.map_or_else(|| "DNF".into(), parse_time);
+
+			draw_text(&format!("{}.", i + 1), width * 0.1, y, size, color);
+			if let Some(sprite) = sprites.get(*id) {
+				draw_texture_ex(
+					&sprite.texture,
+					width * 0.1 + size * 1.8,
+					y - size * 0.8,
+					WHITE,
+					DrawTextureParams {
+						dest_size: Some(vec2(size, size)),
+						source: sprite.first_frame(),
+						..Default::default()
+					},
+				);
+			}
+			draw_text(name, width * 0.1 + size * 3.2, y, size, color);
+			draw_text(&time, width * 0.75, y, size, color);
+		}
+	}
+}
@@ -0,0 +1,145 @@
+use macroquad::prelude::*;
+use serde::Deserialize;
+
+/// How a horse sprite sheet is laid out. Every row is one animation, played left to right.
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct Animation {
+	/// The size of each frame, in pixels
+	pub frame: Vec2,
+	/// Frames per second
+	#[serde(default = "Animation::default_fps")]
+	pub fps: f32,
+	/// The row played while running
+	#[serde(default)]
+	pub run: u32,
+	/// The row played once after every bounce
+	pub bounce: Option<u32>,
+	/// The row played while standing still, before the race and after finishing
+	pub idle: Option<u32>,
+}
+
+/// How a horse turns to face where it is going.
+/// Sprites should face right.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Facing {
+	/// Always drawn the same way
+	#[default]
+	None,
+	/// Mirrored when going left
+	Flip,
+	/// Turned to point where it is going, and mirrored so it is never upside down
+	Rotate,
+}
+
+/// What a horse is doing, which decides the animation row.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pose {
+	/// Running, with how long ago the horse last bounced in seconds
+	Run(f32),
+	Idle,
+}
+
+/// A horse's texture, which may be a sprite sheet.
+#[derive(Clone)]
+pub struct Sprite {
+	pub texture: Texture2D,
+	animation: Option<Animation>,
+	facing: Facing,
+}
+
+impl Animation {
+	fn default_fps() -> f32 {
+		8.
+	}
+
+	/// The frame to draw from a sheet `width` pixels wide.
+	/// Bounces play once from the start, and go back to running when they are over.
+	fn source(&self, width: f32, pose: Pose, time: f32) -> Rect {
+		let frames = (width / self.frame.x).floor().max(1.);
+
+		let (row, frame) = match (pose, self.bounce, self.idle) {
+			(Pose::Run(since), Some(row), _) if since * self.fps < frames => {
+				(row, since * self.fps)
+			}
+			(Pose::Idle, _, Some(row)) => (row, time * self.fps),
+			(Pose::Idle, _, None) => (self.run, 0.),
+			_ => (self.run, time * self.fps),
+		};
+
+		Rect::new(
+			frame.floor() % frames * self.frame.x,
+			row as f32 * self.frame.y,
+			self.frame.x,
+			self.frame.y,
+		)
+	}
+}
+
+impl Sprite {
+	pub fn new(texture: Texture2D, animation: Option<Animation>, facing: Facing) -> Self {
+		Self {
+			texture,
+			animation,
+			facing,
+		}
+	}
+
+	/// The first frame of the sheet, or `None` if the whole texture is the sprite.
+	pub fn first_frame(&self) -> Option<Rect> {
+		self.animation
+			.map(|animation| Rect::new(0., 0., animation.frame.x, animation.frame.y))
+	}
+
+	/// Draws the sprite centered on `pos`, `time` seconds into its animation, facing `dir`.
+	pub fn draw(&self, pos: Vec2, size: f32, dir: Vec2, pose: Pose, time: f32) {
+		let (rotation, flip_x, flip_y) = match self.facing {
+			Facing::None => (0., false, false),
+			Facing::Flip => (0., dir.x < 0., false),
+			Facing::Rotate => (dir.to_angle(), false, dir.x < 0.),
+		};
+
+		draw_texture_ex(
+			&self.texture,
+			pos.x - size / 2.,
+			pos.y - size / 2.,
+			WHITE,
+			DrawTextureParams {
+				dest_size: Some(vec2(size, size)),
+				source: self.source(pose, time),
+				rotation,
+				flip_x,
+				flip_y,
+				..Default::default()
+			},
+		);
+	}
+
+	/// The frame to draw, or `None` if the whole texture is the sprite.
+	fn source(&self, pose: Pose, time: f32) -> Option<Rect> {
+		self.animation
+			.map(|animation| animation.source(self.texture.width(), pose, time))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn frames() {
+		let animation = Animation {
+			frame: vec2(10., 20.),
+			fps: 10.,
+			run: 0,
+			bounce: Some(1),
+			idle: None,
+		};
+		let frame = |pose, time| animation.source(40., pose, time).point();
+
+		assert_eq!(frame(Pose::Run(f32::INFINITY), 0.25), vec2(20., 0.));
+		assert_eq!(frame(Pose::Run(f32::INFINITY), 0.45), vec2(0., 0.));
+		assert_eq!(frame(Pose::Run(0.15), 0.45), vec2(10., 20.));
+		assert_eq!(frame(Pose::Idle, 0.25), vec2(0., 0.));
+	}
+}
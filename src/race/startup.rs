@@ -8,8 +8,11 @@ use crate::{
 	utils::Dirs,
 };
 
+/// How long the countdown lasts if its audio could not be played.
+const SILENT_TIME: f32 = 13.;
+
 pub struct Startup {
-	handle: StreamHandle,
+	handle: Option<StreamHandle>,
 	countdown: Countdown,
 	gate: Gate,
 }
@@ -31,11 +34,11 @@ struct Countdown {
 }
 
 impl Startup {
-	pub async fn new(img: &Image, gate: GateData, countdown: CountdownData) -> Self {
+	pub fn new(img: &Image, gate: GateData, countdown: &CountdownData) -> Self {
 		Self {
 			handle: countdown.play_sound(),
-			countdown: Countdown::new(countdown).await,
-			gate: Gate::new(img, gate).await,
+			countdown: Countdown::new(countdown),
+			gate: Gate::new(img, gate),
 		}
 	}
 
@@ -49,13 +52,16 @@ impl Startup {
 	}
 
 	pub fn done(&self) -> bool {
-		matches!(self.handle.state(), PlaybackState::Stopped)
+		match &self.handle {
+			Some(handle) => matches!(handle.state(), PlaybackState::Stopped),
+			None => self.countdown.time >= SILENT_TIME,
+		}
 	}
 }
 
 impl Gate {
-	async fn new(img: &Image, data: GateData) -> Self {
-		let texture = data.texture().await;
+	fn new(img: &Image, data: GateData) -> Self {
+		let texture = data.texture();
 		let (pos, size) = data.into_pos_size();
 
 		Self {
@@ -91,14 +97,14 @@ impl Countdown {
 		( 24., -24.),
 	];
 
-	async fn new(data: CountdownData) -> Self {
-		let img = data.image().await;
-		let rect = Rect::new(0., 0., img.width as f32, (img.height / 12) as f32);
+	fn new(data: &CountdownData) -> Self {
+		let texture = data.texture();
+		let rect = Rect::new(0., 0., texture.width(), (texture.height() / 12.).floor());
 
 		Self {
 			pos: vec2(screen_width() / 2., screen_height() / 2.),
 			size: Vec2::ZERO,
-			texture: Texture2D::from_image(&img),
+			texture,
 			source: rect,
 			direction: rand::gen_range(0, 3),
 			time: 0.,
@@ -171,6 +177,8 @@ impl Countdown {
 
 impl Drop for Startup {
 	fn drop(&mut self) {
-		self.handle.stop(Tween::default());
+		if let Some(handle) = &mut self.handle {
+			handle.stop(Tween::default());
+		}
 	}
 }
@@ -1,15 +1,11 @@
-use std::{
-	fmt::Debug,
-	path::Path,
-	thread::{JoinHandle, spawn},
-};
+use std::{fmt::Debug, path::Path};
 
 use kira::sound::PlaybackState;
 use macroquad::prelude::*;
 
 use crate::{
 	audio::{StreamHandle, stream},
-	utils::{debug_img, load_img_blocking, render_texture_fullscreen},
+	utils::render_texture_fullscreen,
 };
 
 const ZOOM_TIME: f32 = 4.5;
@@ -17,40 +13,24 @@ const FULL_TIME: f32 = 13.;
 
 pub struct Carrots {
 	pub texture: Texture2D,
-	pub pos: Vec2,
 }
 
 pub struct Victory {
 	time: f32,
 	name: String,
 	zoom: Texture2D,
-	screen: FileLoad,
-	mus: StreamHandle,
-}
-
-enum FileLoad {
-	/// A thread containing the operation
-	Handle(JoinHandle<Image>),
-	/// A texture produced by the operation
-	Texture(Texture2D),
-	/// Used as a temporary value, should only be seen if something goes wrong
-	Poisoned,
+	screen: Texture2D,
+	mus: Option<StreamHandle>,
 }
 
 impl Carrots {
-	pub fn new(pos: Vec2, img: &Image) -> Self {
-		Self {
-			pos,
-			texture: Texture2D::from_image(img),
-		}
+	pub fn new(texture: Texture2D) -> Self {
+		Self { texture }
 	}
 }
 
 impl Victory {
-	pub fn new<Dir>(name: String, screen: Dir, music: Dir) -> Self
-	where
-		Dir: AsRef<Path> + Debug + Send + 'static,
-	{
+	pub fn new(name: String, screen: Texture2D, music: impl AsRef<Path> + Debug) -> Self {
 		let zoom = Texture2D::from_image(&get_screen_data());
 		zoom.set_filter(FilterMode::Nearest);
 
@@ -58,21 +38,22 @@ impl Victory {
 			time: 0.,
 			name,
 			zoom,
-			screen: FileLoad::new(screen),
+			screen,
 			mus: stream(music),
 		}
 	}
 
 	pub fn update(&mut self) {
 		self.time += get_frame_time();
-
-		if self.time >= ZOOM_TIME {
-			self.screen.join();
-		}
 	}
 
 	pub fn should_finish(&self) -> bool {
-		self.mus.state() == PlaybackState::Stopped && self.time > FULL_TIME
+		let stopped = self
+			.mus
+			.as_ref()
+			.is_none_or(|mus| mus.state() == PlaybackState::Stopped);
+
+		stopped && self.time > FULL_TIME
 	}
 
 	pub fn draw(&self) {
@@ -80,12 +61,8 @@ impl Victory {
 			self.zoom();
 			return;
 		}
-		let Some(screen) = self.screen.try_get() else {
-			self.zoom();
-			return;
-		};
 
-		render_texture_fullscreen(screen);
+		render_texture_fullscreen(&self.screen);
 
 		let (width, height) = (screen_width(), screen_height());
 		let current = ((width + height) / 196.) * self.time * 1.25;
@@ -129,40 +106,3 @@ impl Victory {
 		);
 	}
 }
-
-impl FileLoad {
-	fn new<Dir>(path: Dir) -> Self
-	where
-		Dir: AsRef<Path> + Debug + Send + 'static,
-	{
-		Self::Handle(spawn(move || load_img_blocking(&path)))
-	}
-
-	fn try_get(&self) -> Option<&Texture2D> {
-		if let Self::Texture(t) = self {
-			Some(t)
-		} else {
-			None
-		}
-	}
-
-	fn join(&mut self) {
-		if let Self::Handle(_) = self {
-			let owned = std::mem::replace(self, Self::Poisoned);
-
-			let Self::Handle(handle) = owned else {
-				unreachable!("Owned is known to be `Self::Handle(_)`")
-			};
-
-			let img = match handle.join() {
-				Ok(ok) => ok,
-				Err(e) => {
-					tracing::error!("{:?}", e);
-					debug_img()
-				}
-			};
-
-			*self = Self::Texture(Texture2D::from_image(&img));
-		}
-	}
-}
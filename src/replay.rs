@@ -0,0 +1,173 @@
+use std::{
+	fmt::Debug,
+	path::{Path, PathBuf},
+};
+
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	data::{self, RaceData},
+	sim::{Event, Sim},
+};
+
+/// A recording of a race, which can be played back exactly.
+/// Stores every horse's position on every tick, so it does not depend on the physics staying the same.
+#[derive(Serialize, Deserialize, Default)]
+pub struct Replay {
+	seed: u64,
+	race: RaceData,
+	ticks: Vec<Tick>,
+}
+
+/// Records a race as it happens, saving it once the race is over.
+pub struct Recorder {
+	path: PathBuf,
+	replay: Replay,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Tick {
+	/// Every horse's position, in order of id
+	pos: Vec<Vec2>,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	bounces: Vec<usize>,
+	/// Bounces off of other horses
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	hits: Vec<usize>,
+	/// Every horse that triggered an object, and the object it triggered
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	objects: Vec<(usize, usize)>,
+	/// The ids of the horses that reached the carrots, in order
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	finished: Vec<usize>,
+}
+
+impl Replay {
+	pub fn load(path: impl AsRef<Path> + Debug) -> Self {
+		data::read(path)
+	}
+
+	/// The race data the replay was recorded with, including its seed.
+	pub fn race(&self) -> RaceData {
+		self.race.clone().override_seed(Some(self.seed))
+	}
+
+	/// Plays the tick after the last one the simulation has seen.
+	pub fn play(&self, sim: &mut Sim) -> Vec<Event> {
+		let index = sim.ticks() as usize;
+		let Some(tick) = self.ticks.get(index) else {
+			return vec![];
+		};
+
+		sim.step_recorded(
+			&tick.pos,
+			&tick.bounces,
+			&tick.hits,
+			&tick.objects,
+			&tick.finished,
+		)
+	}
+
+	fn record(&mut self, sim: &Sim, events: &[Event]) {
+		let mut tick = Tick {
+			pos: sim.horses().iter().map(|horse| horse.pos).collect(),
+			bounces: vec![],
+			hits: vec![],
+			objects: vec![],
+			finished: vec![],
+		};
+
+		for event in events {
+			match event {
+				Event::Bounce(i) => tick.bounces.push(*i),
+				Event::Hit(i) => tick.hits.push(*i),
+				Event::Object(horse, object) => tick.objects.push((*horse, *object)),
+				Event::Finish(i) => tick.finished.push(*i),
+				Event::Checkpoint(_) | Event::Out(_) => (),
+			}
+		}
+
+		self.ticks.push(tick);
+	}
+}
+
+impl Recorder {
+	pub fn new(path: PathBuf, race: &RaceData) -> Self {
+		Self {
+			path,
+			replay: Replay {
+				seed: race.seed().unwrap_or_default(),
+				race: race.clone(),
+				ticks: vec![],
+			},
+		}
+	}
+
+	/// Records the state of the simulation after a tick.
+	pub fn record(&mut self, sim: &Sim, events: &[Event]) {
+		self.replay.record(sim, events);
+
+		if sim.is_over() {
+			self.save();
+		}
+	}
+
+	fn save(&self) {
+		let str = match toml::to_string(&self.replay) {
+			Ok(ok) => ok,
+			Err(e) => {
+				tracing::error!("Failed to encode replay: {e}");
+				return;
+			}
+		};
+
+		match std::fs::write(&self.path, str) {
+			Ok(()) => tracing::info!("Replay saved to {:?}", self.path),
+			Err(e) => tracing::error!("Failed to save replay {:?}: {e}", self.path),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		data::{Stats, WinData},
+		sim::tests::arena,
+	};
+
+	fn sim(finishers: usize) -> Sim {
+		let horses = vec![
+			(vec2(100., 100.), WinData::default(), Stats::default()),
+			(vec2(200., 200.), WinData::default(), Stats::default()),
+		];
+
+		Sim::new(arena(), vec2(250., 50.), horses, 99).with_finishers(finishers)
+	}
+
+	#[test]
+	fn round_trip() {
+		for finishers in [1, 0] {
+			let mut replay = Replay::default();
+			let outcome = sim(finishers).run(600., |sim, events| replay.record(sim, events));
+
+			let str = toml::to_string(&replay).unwrap();
+			let replay: Replay = toml::from_str(&str).unwrap();
+
+			let mut played = sim(finishers);
+			for _ in 0..replay.ticks.len() {
+				replay.play(&mut played);
+			}
+
+			assert!(played.winner().is_some());
+			assert!(played.is_over());
+			assert_eq!(outcome.time.to_bits(), played.time().to_bits());
+			assert_eq!(outcome.finished, played.finished());
+			assert_eq!(
+				outcome.winner.map(|horse| horse.pos),
+				played.winner().map(|horse| horse.pos)
+			);
+		}
+	}
+}
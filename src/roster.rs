@@ -0,0 +1,172 @@
+use std::{
+	collections::BTreeMap,
+	fmt::{self, Display},
+	path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	data::{self, read},
+	error::warn,
+	race::parse_time,
+	sim::Sim,
+};
+
+/// The career of every horse that has raced, kept in a file between races.
+pub struct Roster {
+	path: PathBuf,
+	careers: Careers,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Careers {
+	/// Keyed by the path of the horse's file
+	horses: BTreeMap<String, Career>,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+pub struct Career {
+	/// The name the horse last raced under
+	pub name: String,
+	pub races: u32,
+	pub wins: u32,
+	/// The fastest the horse has reached the carrots, in seconds
+	pub best_time: Option<f32>,
+	pub bounces: u64,
+}
+
+impl Roster {
+	/// Loads every career from a file, or starts a new roster if there is no file yet.
+	pub fn load(path: impl Into<PathBuf>) -> Self {
+		let path = path.into();
+		let careers = if path.exists() {
+			read::<Careers>(&path)
+		} else {
+			Careers::default()
+		};
+
+		Self { path, careers }
+	}
+
+	pub fn save(&self) {
+		match data::write(&self.path, &self.careers) {
+			Ok(()) => tracing::info!("Roster saved to \"{}\"", self.path.display()),
+			Err(e) => warn(&e),
+		}
+	}
+
+	/// Adds a finished race to the career of every horse in it.
+	/// `entrants` are the files of the horses in the simulation, and `bounces` how many times each of them bounced, both in order of id.
+	pub fn record(&mut self, entrants: &[PathBuf], sim: &Sim, bounces: &[u32]) {
+		let winner = sim.finished().first().map(|finish| finish.horse);
+
+		for horse in sim.horses() {
+			let Some(path) = entrants.get(horse.id) else {
+				continue;
+			};
+			let career = self.careers.horses.entry(key(path)).or_default();
+
+			career.name = horse.win_data.name().into();
+			career.races += 1;
+			career.bounces += bounces.get(horse.id).copied().unwrap_or(0) as u64;
+
+			if winner == Some(horse.id) {
+				career.wins += 1;
+			}
+			if let Some(finish) = sim
+				.finished()
+				.iter()
+				.find(|finish| finish.horse == horse.id)
+			{
+				career.best_time = Some(
+					career
+						.best_time
+						.map_or(finish.time, |best| best.min(finish.time)),
+				);
+			}
+		}
+	}
+
+	#[cfg(test)]
+	pub fn career(&self, path: impl AsRef<Path>) -> Option<&Career> {
+		self.careers.horses.get(&key(path.as_ref()))
+	}
+}
+
+impl Display for Roster {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if self.careers.horses.is_empty() {
+			return write!(f, "No horses have raced yet");
+		}
+
+		writeln!(
+			f,
+			"{:<24} {:>6} {:>6} {:>7} {:>10} {:>8}",
+			"Horse", "Races", "Wins", "Win %", "Best time", "Bounces"
+		)?;
+
+		let mut careers = self.careers.horses.values().collect::<Vec<_>>();
+		careers.sort_by(|a, b| b.wins.cmp(&a.wins).then_with(|| a.name.cmp(&b.name)));
+
+		for career in careers {
+			let percent = if career.races == 0 {
+				0.
+			} else {
+				career.wins as f32 / career.races as f32 * 100.
+			};
+			let best_time = career.best_time.map_or_else(|| "-".into(), parse_time);
+
+			writeln!(
+				f,
+				"{:<24} {:>6} {:>6} {:>6.1}% {:>10} {:>8}",
+				career.name, career.races, career.wins, percent, best_time, career.bounces
+			)?;
+		}
+		Ok(())
+	}
+}
+
+/// Horse files are stored by path, which is written the same way on every platform.
+fn key(path: &Path) -> String {
+	path.to_string_lossy().replace('\\', "/")
+}
+
+#[cfg(test)]
+mod tests {
+	use macroquad::prelude::*;
+
+	use super::*;
+	use crate::data::{Stats, WinData};
+
+	#[test]
+	fn record() {
+		let horses = vec![
+			(vec2(1., 1.), WinData::named("a"), Stats::default()),
+			(vec2(2., 2.), WinData::named("b"), Stats::default()),
+		];
+		let mut sim = Sim::new(Image::empty(), Vec2::ZERO, horses, 1).with_finishers(2);
+		sim.step_recorded(&[vec2(1., 1.), vec2(2., 2.)], &[], &[], &[], &[1]);
+		sim.step_recorded(&[vec2(1., 1.), vec2(2., 2.)], &[], &[], &[], &[0]);
+
+		let entrants = ["a.toml".into(), "b.toml".into()];
+		let mut roster = Roster {
+			path: PathBuf::new(),
+			careers: Careers::default(),
+		};
+		roster.record(&entrants, &sim, &[3, 4]);
+		roster.record(&entrants, &sim, &[1, 1]);
+
+		let (a, b) = (
+			roster.career("a.toml").unwrap(),
+			roster.career("b.toml").unwrap(),
+		);
+		assert_eq!((a.races, a.wins, a.bounces), (2, 0, 4));
+		assert_eq!((b.races, b.wins, b.bounces), (2, 2, 5));
+		// Every finisher gets their own time, not when the race ended
+		assert_eq!(b.best_time, Some(sim.finished()[0].time));
+		assert_eq!(a.best_time, Some(sim.time()));
+		assert!(b.best_time < a.best_time);
+		assert_eq!(a.name, "a");
+	}
+}
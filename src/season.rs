@@ -0,0 +1,249 @@
+use std::{collections::VecDeque, path::PathBuf};
+
+use hashbrown::HashMap;
+use macroquad::prelude::*;
+
+use crate::{
+	Scene,
+	cli::RaceArgs,
+	data::{RaceData, WinData},
+	race::{Loading, Race, victory::Victory},
+	roster::Roster,
+};
+
+/// How long the standings are shown between races, unless skipped.
+const STANDINGS_TIME: f32 = 10.;
+
+/// Several races run back to back, with points awarded after each one.
+pub struct Season {
+	races: VecDeque<PathBuf>,
+	played: usize,
+	points: Vec<u32>,
+	standings: Standings,
+	stage: Stage,
+	/// Merged over every race in the season
+	args: RaceArgs,
+	/// Where the careers of the horses are kept
+	roster: PathBuf,
+}
+
+/// Points per horse, keyed by name.
+#[derive(Default)]
+pub struct Standings {
+	table: HashMap<String, Standing>,
+}
+
+struct Standing {
+	win_data: WinData,
+	points: u32,
+}
+
+enum Stage {
+	Loading(Loading),
+	Race(Box<Race>),
+	Standings(f32),
+	Champion(Box<Victory>),
+	Done,
+}
+
+impl Season {
+	pub fn new(races: Vec<PathBuf>, points: Vec<u32>, args: RaceArgs, roster: PathBuf) -> Self {
+		let mut season = Self {
+			races: races.into(),
+			played: 0,
+			points,
+			standings: Standings::default(),
+			stage: Stage::Done,
+			args,
+			roster,
+		};
+		season.next();
+		season
+	}
+
+	/// Moves on to the next race, or to the champion if there are none left.
+	fn next(&mut self) {
+		self.stage = if let Some(path) = self.races.pop_front() {
+			tracing::info!("Starting race {} of the season: {path:?}", self.played + 1);
+			let data = RaceData::load(path).with_overrides(&self.args).set_seed();
+			Stage::Loading(data.preload())
+		} else if let Some(champion) = self.standings.champion() {
+			Stage::Champion(Box::new(champion.clone().into_victory()))
+		} else {
+			tracing::warn!("Season ended without a champion");
+			Stage::Done
+		};
+	}
+
+	fn finish_race(&mut self, race: &Race) {
+		self.played += 1;
+
+		for horse in race.sim().horses() {
+			self.standings.enter(&horse.win_data);
+		}
+		// Horses that did not reach the carrots are placed by the standings, the same way bets are settled
+		let sim = race.sim();
+		for (horse, points) in sim.standings().into_iter().zip(&self.points) {
+			self.standings
+				.award(sim.horses()[horse].win_data.name(), *points);
+		}
+
+		self.stage = Stage::Standings(0.);
+	}
+
+	fn draw_standings(&self) {
+		clear_background(BLACK);
+
+		let (width, height) = (screen_width(), screen_height());
+		let size = (width + height) / 50.;
+		let total = self.played + self.races.len();
+
+		draw_text(
+			&format!("Standings after race {} of {total}", self.played),
+			width * 0.1,
+			height * 0.15,
+			size * 1.5,
+			WHITE,
+		);
+
+		for (i, standing) in self.standings.sorted().iter().enumerate() {
+			let y = height * 0.25 + size * 1.2 * i as f32;
+			let color = if i == 0 { GOLD } else { WHITE };
+
+			draw_text(
+				&format!("{}. {}", i + 1, standing.win_data.name()),
+				width * 0.1,
+				y,
+				size,
+				color,
+			);
+			draw_text(
+				&format!("{} pts", standing.points),
+				width * 0.75,
+				y,
+				size,
+				color,
+			);
+		}
+	}
+}
+
+impl Scene for Season {
+	fn update(&mut self) {
+		match &mut self.stage {
+			Stage::Loading(loading) => {
+				if loading.is_done() {
+					let Stage::Loading(loading) = std::mem::replace(&mut self.stage, Stage::Done)
+					else {
+						unreachable!("Stage is known to be `Stage::Loading(_)`")
+					};
+					let mut race = loading.finish();
+					race.track(Roster::load(&self.roster));
+					self.stage = Stage::Race(Box::new(race));
+				}
+			}
+			Stage::Race(race) => {
+				race.update();
+				if race.should_finish() {
+					let Stage::Race(race) = std::mem::replace(&mut self.stage, Stage::Done) else {
+						unreachable!("Stage is known to be `Stage::Race(_)`")
+					};
+					self.finish_race(&race);
+				}
+			}
+			Stage::Standings(time) => {
+				*time += get_frame_time();
+				if *time >= STANDINGS_TIME
+					|| is_key_pressed(KeyCode::Space)
+					|| is_key_pressed(KeyCode::Enter)
+				{
+					self.next();
+				}
+			}
+			Stage::Champion(victory) => victory.update(),
+			Stage::Done => (),
+		}
+	}
+
+	fn draw(&self) {
+		match &self.stage {
+			Stage::Loading(loading) => loading.draw(),
+			Stage::Race(race) => race.draw(),
+			Stage::Standings(_) => self.draw_standings(),
+			Stage::Champion(victory) => victory.draw(),
+			Stage::Done => (),
+		}
+	}
+
+	fn should_finish(&self) -> bool {
+		match &self.stage {
+			Stage::Champion(victory) => victory.should_finish(),
+			Stage::Done => true,
+			_ => false,
+		}
+	}
+}
+
+impl Standings {
+	/// Adds a horse to the table, if it is not already there.
+	pub fn enter(&mut self, win_data: &WinData) {
+		self.table
+			.entry_ref(win_data.name())
+			.or_insert_with(|| Standing {
+				win_data: win_data.clone(),
+				points: 0,
+			});
+	}
+
+	pub fn award(&mut self, name: &str, points: u32) {
+		if let Some(standing) = self.table.get_mut(name) {
+			standing.points += points;
+		}
+	}
+
+	/// The horse with the most points, if any have been awarded.
+	pub fn champion(&self) -> Option<&WinData> {
+		self.sorted()
+			.first()
+			.filter(|standing| standing.points > 0)
+			.map(|standing| &standing.win_data)
+	}
+
+	/// Every horse, from most points to least.
+	/// Ties are broken by name, so the order is always the same.
+	fn sorted(&self) -> Vec<&Standing> {
+		let mut sorted = self.table.values().collect::<Vec<_>>();
+		sorted.sort_by(|a, b| {
+			b.points
+				.cmp(&a.points)
+				.then_with(|| a.win_data.name().cmp(b.win_data.name()))
+		});
+		sorted
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn standings_order() {
+		let mut standings = Standings::default();
+		assert!(standings.champion().is_none());
+
+		for name in ["b", "a", "c"] {
+			standings.enter(&WinData::named(name));
+		}
+		standings.award("c", 3);
+		standings.award("b", 1);
+		standings.award("a", 1);
+
+		let names = standings
+			.sorted()
+			.iter()
+			.map(|standing| standing.win_data.name())
+			.collect::<Vec<_>>();
+		assert_eq!(names, ["c", "a", "b"]);
+		assert_eq!(standings.champion().map(WinData::name), Some("c"));
+	}
+}
@@ -0,0 +1,862 @@
+use std::sync::Arc;
+
+use macroquad::{prelude::*, rand::RandGenerator};
+
+use bounce::{Bounce, exchange};
+use field::DistanceField;
+use geometry::Geometry;
+use goal::{Goal, Reached};
+use grid::Grid;
+use horse::{Collisions, DIR_WIDTH, Horse, NO_COLLISION, away, probe_normal};
+use object::{Effect, Object};
+
+use crate::data::{Stats, WinData};
+
+pub mod bounce;
+pub mod estimate;
+pub mod field;
+pub mod geometry;
+pub mod goal;
+pub mod grid;
+pub mod horse;
+pub mod object;
+
+/// The length of a single simulation tick, in seconds.
+pub const TICK: f32 = 1. / 60.;
+
+/// The race simulation, without any rendering or audio.
+/// Given the same arena, horses and seed, it will always produce the same race.
+pub struct Sim {
+	ticks: u32,
+	/// Shared, so many races can be simulated in the same arena without copying it
+	arena: Arc<Image>,
+	carrots: Vec2,
+	horses: Vec<Horse>,
+	rng: RandGenerator,
+	/// Every horse that has reached the carrots, in the order they did
+	finished: Vec<Finish>,
+	/// How many horses have to reach the carrots for the race to end
+	finishers: usize,
+	/// Used to rank horses by how far they have to walk, instead of in a straight line
+	field: Option<Arc<DistanceField>>,
+	goal: Goal,
+	/// How many checkpoints each horse has touched
+	progress: Vec<usize>,
+	/// Every horse that has been knocked out by a hazard, in the order they were
+	out: Vec<usize>,
+	objects: Vec<Object>,
+	/// Walls made of segments, which horses collide with as circles instead of probing the arena
+	geometry: Option<Arc<Geometry>>,
+	bounce: Bounce,
+	/// Every horse and object it is inside of, so objects only trigger when a horse enters them
+	inside: Vec<(usize, usize)>,
+}
+
+/// Something that happened during a tick, which the frontend may want to react to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+	/// The horse with the given id bounced off of a wall or object
+	Bounce(usize),
+	/// The horse with the given id bounced off of another horse
+	Hit(usize),
+	/// The horse with the given id reached the carrots
+	Finish(usize),
+	/// The horse with the given id touched its next checkpoint
+	Checkpoint(usize),
+	/// The horse with the given id touched a hazard, and is out of the race
+	Out(usize),
+	/// The horse with the given id triggered the object at the given index
+	Object(usize, usize),
+}
+
+/// A horse reaching the carrots.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Finish {
+	/// The id of the horse
+	pub horse: usize,
+	/// How long it took, in seconds
+	pub time: f32,
+}
+
+/// The result of a completed simulation.
+pub struct Outcome {
+	pub winner: Option<Horse>,
+	pub time: f32,
+	pub finished: Vec<Finish>,
+}
+
+impl Sim {
+	pub fn new(
+		arena: impl Into<Arc<Image>>,
+		carrots: Vec2,
+		horses: Vec<(Vec2, WinData, Stats)>,
+		seed: u64,
+	) -> Self {
+		let rng = RandGenerator::new();
+		rng.srand(seed);
+
+		let horses = horses
+			.into_iter()
+			.enumerate()
+			.map(|(id, (pos, win_data, stats))| Horse::new(id, pos, win_data, stats, &rng))
+			.collect::<Vec<_>>();
+
+		Self {
+			ticks: 0,
+			arena: arena.into(),
+			carrots,
+			rng,
+			finished: vec![],
+			finishers: 1,
+			field: None,
+			goal: Goal::default(),
+			progress: vec![0; horses.len()],
+			out: vec![],
+			objects: vec![],
+			geometry: None,
+			bounce: Bounce::default(),
+			inside: vec![],
+			horses,
+		}
+	}
+
+	/// Ranks horses that have not reached the carrots by how far they have to walk to them.
+	pub fn with_field(mut self, field: impl Into<Arc<DistanceField>>) -> Self {
+		self.field = Some(field.into());
+		self
+	}
+
+	/// Changes how the race is won, which is reaching the carrots by default.
+	pub fn with_goal(mut self, goal: Goal) -> Self {
+		self.goal = goal;
+		self
+	}
+
+	/// Makes horses collide as circles with these walls, and with each other, instead of probing the arena.
+	pub fn with_geometry(mut self, geometry: impl Into<Arc<Geometry>>) -> Self {
+		self.geometry = Some(geometry.into());
+		self
+	}
+
+	/// Changes how horses bounce off of things, which is [`Bounce::Classic`] by default.
+	pub fn with_bounce(mut self, bounce: Bounce) -> Self {
+		self.bounce = bounce;
+		self
+	}
+
+	/// Places objects in the arena for horses to run into.
+	pub fn with_objects(mut self, objects: Vec<Object>) -> Self {
+		self.objects = objects;
+		self
+	}
+
+	/// Keeps the race going until this many horses reach the carrots, or every horse if it is 0.
+	pub fn with_finishers(mut self, finishers: usize) -> Self {
+		self.finishers = match finishers {
+			0 => self.horses.len(),
+			finishers => finishers,
+		};
+		self
+	}
+
+	/// Advances the simulation by one [`TICK`].
+	/// Horses that have reached the carrots or are out stay where they are, and are out of the way of everyone else.
+	/// Does nothing once the race is over.
+	pub fn step(&mut self) -> Vec<Event> {
+		let mut events = vec![];
+		if self.is_over() {
+			return events;
+		}
+
+		self.ticks += 1;
+
+		// Horses push each other along when they reflect, instead of bouncing off like walls
+		let classic = self.bounce.is_classic();
+		let grid = self.grid();
+		let pairs = if classic { vec![] } else { self.pairs(&grid) };
+
+		let collisions = self
+			.horses
+			.iter()
+			.enumerate()
+			.map(|(i, horse)| {
+				if !self.is_running(i) {
+					return (None, None, false);
+				}
+				if let Some(geometry) = &self.geometry {
+					let wall = geometry.contact(horse.pos, horse.radius());
+					return self.contact(i, wall, classic.then_some(&grid));
+				}
+				if !classic {
+					// Reflecting needs a real normal, from every probe that touches a wall
+					let wall = probe_normal(horse.collision_wall(&self.arena));
+					return self.contact(i, wall, None);
+				}
+
+				// The original eight probes, for walls, objects and other horses alike
+				let (bumps, bumped) = self.collision_objects(horse);
+				let near = grid.near(horse.pos);
+				let honses = horse.collision_honses(near.into_iter().map(|j| &self.horses[j]));
+				let collisions = horse.collision_wall(&self.arena) | bumps | honses;
+				// The horse bounces off of whatever is on the side it bounces away from
+				let hit = honses != NO_COLLISION && away(honses) == away(collisions);
+				(away(collisions), bumped, hit)
+			})
+			.collect::<Vec<(Option<Vec2>, Option<usize>, bool)>>();
+
+		for (i, (away, bumped, hit)) in collisions.into_iter().enumerate() {
+			if !self.is_running(i) {
+				continue;
+			}
+
+			let target = self.target(i);
+			let honse = &mut self.horses[i];
+			honse.update();
+
+			// Reflecting off of something the horse is moving away from would send it back in
+			if let Some(away) = away
+				&& (classic || honse.dir.dot(away) < 0.)
+			{
+				honse.bounce(away, target, self.bounce, &self.rng);
+				events.push(if hit { Event::Hit(i) } else { Event::Bounce(i) });
+
+				// Only bounces trigger bumpers, so they do not keep speeding up horses on their way out
+				if let Some(object) = bumped {
+					if let Effect::Bumper { factor } = self.objects[object].effect {
+						honse.speed *= factor;
+					}
+					events.push(Event::Object(i, object));
+				}
+			}
+		}
+
+		if let Bounce::Reflect { restitution, .. } = self.bounce {
+			for (i, j, normal) in pairs {
+				let (left, right) = self.horses.split_at_mut(j);
+				if exchange(&mut left[i], &mut right[0], normal, restitution) {
+					events.extend([Event::Hit(i), Event::Hit(j)]);
+				}
+			}
+		}
+
+		self.trigger_objects(&mut events);
+		self.check_goal(true, &mut events);
+
+		events
+	}
+
+	/// Advances the simulation by one [`TICK`], moving the horses to recorded positions instead of running physics.
+	/// Used to play back a [`crate::replay::Replay`].
+	pub fn step_recorded(
+		&mut self,
+		pos: &[Vec2],
+		bounces: &[usize],
+		hits: &[usize],
+		triggered: &[(usize, usize)],
+		finished: &[usize],
+	) -> Vec<Event> {
+		let mut events = vec![];
+		if self.is_over() {
+			return events;
+		}
+
+		self.ticks += 1;
+
+		for (horse, pos) in self.horses.iter_mut().zip(pos) {
+			// Directions are not recorded, but sprites still need to face where the horse is going
+			if let Some(dir) = (*pos - horse.pos).try_normalize() {
+				horse.dir = dir;
+			}
+			horse.pos = *pos;
+		}
+		events.extend(bounces.iter().map(|i| Event::Bounce(*i)));
+		events.extend(hits.iter().map(|i| Event::Hit(*i)));
+		events.extend(
+			triggered
+				.iter()
+				.map(|(horse, object)| Event::Object(*horse, *object)),
+		);
+		// Checkpoints and hazards only depend on where the horses are, so they are not recorded
+		self.check_goal(false, &mut events);
+
+		for i in finished {
+			self.finish(*i);
+			events.push(Event::Finish(*i));
+		}
+
+		events
+	}
+
+	/// Runs the simulation until the race is over, or until `limit` seconds have passed.
+	/// `on_tick` is called after every tick, with the events it produced.
+	pub fn run(mut self, limit: f32, mut on_tick: impl FnMut(&Self, &[Event])) -> Outcome {
+		while !self.is_over() && self.time() < limit {
+			let events = self.step();
+			on_tick(&self, &events);
+		}
+
+		Outcome {
+			time: self.time(),
+			winner: self
+				.finished
+				.first()
+				.map(|finish| self.horses.swap_remove(finish.horse)),
+			finished: self.finished,
+		}
+	}
+
+	/// If enough horses have reached the carrots to end the race.
+	/// Horses that are out cannot finish, so they do not count.
+	pub fn is_over(&self) -> bool {
+		let left = self.horses.len() - self.out.len();
+		self.finished.len() >= self.finishers.min(left).max(1)
+	}
+
+	/// The running horses, sorted into cells as wide as any two horses can reach.
+	fn grid(&self) -> Grid {
+		let running = self
+			.horses
+			.iter()
+			.enumerate()
+			.filter(|(i, _)| self.is_running(*i))
+			.map(|(i, horse)| (i, horse.pos));
+		Grid::new(self.reach(), running)
+	}
+
+	/// The furthest apart the centers of two horses can be and still touch.
+	fn reach(&self) -> f32 {
+		self.horses
+			.iter()
+			.map(|horse| horse.radius() * 2.)
+			.fold(0., f32::max)
+	}
+
+	/// Every pair of running horses that are touching, and the direction from the second to the first.
+	/// The first horse always comes before the second.
+	fn pairs(&self, grid: &Grid) -> Vec<(usize, usize, Vec2)> {
+		let mut pairs = vec![];
+		for (i, horse) in self.horses.iter().enumerate() {
+			if !self.is_running(i) {
+				continue;
+			}
+			for j in grid.near(horse.pos) {
+				if j <= i {
+					continue;
+				}
+
+				let (a, b) = (&self.horses[i], &self.horses[j]);
+				if let Some(normal) = (a.pos - b.pos).try_normalize()
+					&& a.pos.distance(b.pos) < a.radius() + b.radius()
+				{
+					pairs.push((i, j, normal));
+				}
+			}
+		}
+		pairs
+	}
+
+	/// Which way a horse bounces off of everything its circle overlaps, the first solid object it overlaps, and if it overlaps another horse.
+	/// `wall` is the normal of the walls it touches, if any. Other horses are only bounced off of if the `grid` they are in is given.
+	/// Horses that are already moving away from what they overlap do not bounce again.
+	fn contact(
+		&self,
+		i: usize,
+		wall: Option<Vec2>,
+		grid: Option<&Grid>,
+	) -> (Option<Vec2>, Option<usize>, bool) {
+		let horse = &self.horses[i];
+		let mut touching = wall.is_some();
+		let mut normal = wall.unwrap_or_default();
+		let mut bumped = None;
+
+		let others = grid.and_then(|grid| self.overlap(i, grid));
+		if let Some(others) = others {
+			touching = true;
+			normal += others;
+		}
+		for (o, object) in self.objects.iter().enumerate() {
+			let pos = object.pos_at(self.ticks);
+			if object.is_solid() && horse.pos.distance(pos) < horse.radius() + object.radius {
+				touching = true;
+				normal += (horse.pos - pos).normalize_or_zero();
+				bumped = bumped.or(Some(o));
+			}
+		}
+
+		let normal = normal.try_normalize().unwrap_or(-horse.dir);
+		if !touching || horse.dir.dot(normal) >= 0. {
+			return (None, None, false);
+		}
+		(Some(normal * DIR_WIDTH), bumped, others.is_some())
+	}
+
+	/// The directions away from every running horse a horse's circle overlaps, added up.
+	/// `None` if it does not overlap any.
+	fn overlap(&self, i: usize, grid: &Grid) -> Option<Vec2> {
+		let horse = &self.horses[i];
+		let mut normal = None;
+		for j in grid.near(horse.pos) {
+			let other = &self.horses[j];
+			if j != i && horse.pos.distance(other.pos) < horse.radius() + other.radius() {
+				*normal.get_or_insert_default() += (horse.pos - other.pos).normalize_or_zero();
+			}
+		}
+		normal
+	}
+
+	/// Which sides of a horse touch a solid object, and the first object it touches.
+	fn collision_objects(&self, horse: &Horse) -> (Collisions, Option<usize>) {
+		let mut collisions = NO_COLLISION;
+		let mut bumped = None;
+
+		for (i, object) in self.objects.iter().enumerate() {
+			if !object.is_solid() {
+				continue;
+			}
+
+			let collision = horse.collision_circle(object.pos_at(self.ticks), object.radius);
+			if collision != NO_COLLISION {
+				collisions |= collision;
+				bumped = bumped.or(Some(i));
+			}
+		}
+
+		(collisions, bumped)
+	}
+
+	/// Applies every object that running horses are over, which is not solid.
+	/// Mud slows horses down for as long as they are in it, and everything else triggers once when a horse enters it.
+	fn trigger_objects(&mut self, events: &mut Vec<Event>) {
+		for i in 0..self.horses.len() {
+			if !self.is_running(i) {
+				continue;
+			}
+
+			for (o, object) in self.objects.iter().enumerate() {
+				if object.is_solid() {
+					continue;
+				}
+
+				let horse = &mut self.horses[i];
+				if horse.pos.distance(object.pos_at(self.ticks)) > object.radius {
+					self.inside.retain(|inside| *inside != (i, o));
+					continue;
+				}
+
+				if let Effect::Mud { max_speed } = object.effect {
+					horse.speed = horse.speed.min(max_speed);
+				}
+				if self.inside.contains(&(i, o)) {
+					continue;
+				}
+				self.inside.push((i, o));
+				events.push(Event::Object(i, o));
+
+				match object.effect {
+					Effect::Boost { factor } => horse.speed *= factor,
+					Effect::Teleporter { to } => {
+						horse.pos = to;
+						// Arriving in another teleporter should not send the horse straight back
+						for (other, object) in self.objects.iter().enumerate() {
+							if to.distance(object.pos_at(self.ticks)) <= object.radius
+								&& !self.inside.contains(&(i, other))
+							{
+								self.inside.push((i, other));
+							}
+						}
+					}
+					_ => (),
+				}
+			}
+		}
+	}
+
+	/// Checks every running horse against the goal, finishing them only if `finish` is set.
+	fn check_goal(&mut self, finish: bool, events: &mut Vec<Event>) {
+		for i in 0..self.horses.len() {
+			if self.is_over() || !self.is_running(i) {
+				continue;
+			}
+
+			match self
+				.goal
+				.reached(&self.horses[i], self.progress[i], self.carrots)
+			{
+				Some(Reached::Checkpoint) => {
+					self.progress[i] += 1;
+					events.push(Event::Checkpoint(i));
+				}
+				Some(Reached::Finish) if finish => {
+					self.finish(i);
+					events.push(Event::Finish(i));
+				}
+				// The last horse left is never knocked out, since it has won
+				Some(Reached::Hazard) if self.running().count() > 1 => {
+					self.out.push(i);
+					events.push(Event::Out(i));
+				}
+				_ => (),
+			}
+		}
+
+		if finish && !self.goal.uses_carrots() && self.finished.is_empty() {
+			let mut left = (0..self.horses.len()).filter(|i| self.is_running(*i));
+			if let (Some(winner), None) = (left.next(), left.next()) {
+				self.finish(winner);
+				events.push(Event::Finish(winner));
+			}
+		}
+	}
+
+	fn is_finished(&self, horse: usize) -> bool {
+		self.finished.iter().any(|finish| finish.horse == horse)
+	}
+
+	/// If a horse has been knocked out by a hazard.
+	pub fn is_out(&self, horse: usize) -> bool {
+		self.out.contains(&horse)
+	}
+
+	/// If a horse has neither reached the carrots nor is out.
+	pub fn is_running(&self, horse: usize) -> bool {
+		!self.is_finished(horse) && !self.is_out(horse)
+	}
+
+	fn finish(&mut self, horse: usize) {
+		self.finished.push(Finish {
+			horse,
+			time: self.time(),
+		});
+	}
+
+	/// Every horse that has not reached the carrots yet, and is not out.
+	fn running(&self) -> impl Iterator<Item = &Horse> {
+		self.horses
+			.iter()
+			.enumerate()
+			.filter(|(i, _)| self.is_running(*i))
+			.map(|(_, horse)| horse)
+	}
+
+	pub fn ticks(&self) -> u32 {
+		self.ticks
+	}
+
+	/// The amount of simulated time, in seconds.
+	pub fn time(&self) -> f32 {
+		self.ticks as f32 * TICK
+	}
+
+	pub fn arena(&self) -> &Image {
+		&self.arena
+	}
+
+	pub fn carrots(&self) -> Vec2 {
+		self.carrots
+	}
+
+	pub fn geometry(&self) -> Option<&Geometry> {
+		self.geometry.as_deref()
+	}
+
+	pub fn field(&self) -> Option<&DistanceField> {
+		self.field.as_deref()
+	}
+
+	/// Every object in the arena, and where it is on the current tick.
+	pub fn objects(&self) -> impl Iterator<Item = (&Object, Vec2)> {
+		self.objects
+			.iter()
+			.map(|object| (object, object.pos_at(self.ticks)))
+	}
+
+	pub fn goal(&self) -> &Goal {
+		&self.goal
+	}
+
+	/// How many checkpoints a horse has touched.
+	pub fn progress(&self, horse: usize) -> usize {
+		self.progress[horse]
+	}
+
+	/// Where a horse is headed next: its next checkpoint, or else the carrots.
+	fn target(&self, horse: usize) -> Vec2 {
+		self.goal
+			.checkpoint(self.progress[horse])
+			.unwrap_or(self.carrots)
+	}
+
+	/// How far a horse is from where it is headed.
+	/// Walking distance to the carrots if there is a [`DistanceField`], where horses that cannot reach them are infinitely far away.
+	/// Every horse is the same distance away in a race without a place to reach.
+	pub fn distance(&self, horse: usize) -> f32 {
+		let pos = self.horses[horse].pos;
+		let to_carrots = || match &self.field {
+			Some(field) => field.distance(pos).unwrap_or(f32::INFINITY),
+			None => pos.distance(self.carrots),
+		};
+
+		match &self.goal {
+			Goal::Survival { .. } => 0.,
+			Goal::Any { points } => points
+				.iter()
+				.map(|point| pos.distance(*point))
+				.fold(to_carrots(), f32::min),
+			goal => match goal.checkpoint(self.progress[horse]) {
+				Some(checkpoint) => pos.distance(checkpoint),
+				None => to_carrots(),
+			},
+		}
+	}
+
+	/// Every horse, in order of id.
+	pub fn horses(&self) -> &[Horse] {
+		&self.horses
+	}
+
+	pub fn winner(&self) -> Option<&Horse> {
+		self.finished
+			.first()
+			.map(|finish| &self.horses[finish.horse])
+	}
+
+	/// Every horse that has reached the carrots, in the order they did.
+	pub fn finished(&self) -> &[Finish] {
+		&self.finished
+	}
+
+	/// The id of every horse, from first place to last.
+	/// Horses that are still running are ranked by how many checkpoints they have touched, then by how close they are to the next one.
+	/// Horses that are out come last, with the last one knocked out ahead of the rest.
+	pub fn standings(&self) -> Vec<usize> {
+		let mut running = (0..self.horses.len())
+			.filter(|i| self.is_running(*i))
+			.collect::<Vec<_>>();
+		running.sort_by(|a, b| {
+			self.progress[*b]
+				.cmp(&self.progress[*a])
+				.then_with(|| self.distance(*a).total_cmp(&self.distance(*b)))
+		});
+
+		self.finished
+			.iter()
+			.map(|finish| finish.horse)
+			.chain(running)
+			.chain(self.out.iter().rev().copied())
+			.collect()
+	}
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+	use super::*;
+	use crate::sim::horse::DIR_WIDTH;
+
+	pub(crate) fn arena() -> Image {
+		let mut img = Image::gen_image_color(400, 400, BLANK);
+		for i in 0..400 {
+			img.set_pixel(i, 0, WHITE);
+			img.set_pixel(i, 399, WHITE);
+			img.set_pixel(0, i, WHITE);
+			img.set_pixel(399, i, WHITE);
+		}
+		img
+	}
+
+	fn sim(seed: u64) -> Sim {
+		let horses = vec![
+			(vec2(100., 100.), WinData::default(), Stats::default()),
+			(vec2(200., 100.), WinData::default(), Stats::default()),
+			(vec2(100., 200.), WinData::default(), Stats::default()),
+		];
+		Sim::new(arena(), vec2(300., 300.), horses, seed)
+	}
+
+	#[test]
+	fn deterministic() {
+		let a = sim(1234).run(600., |_, _| ());
+		let b = sim(1234).run(600., |_, _| ());
+
+		assert!(a.winner.is_some());
+		assert_eq!(a.time.to_bits(), b.time.to_bits());
+		assert_eq!(a.winner.map(|h| h.pos), b.winner.map(|h| h.pos));
+	}
+
+	#[test]
+	fn checkpoints() {
+		let checkpoints = vec![vec2(300., 100.), vec2(100., 300.)];
+		let mut sim = sim(1234).with_goal(Goal::Checkpoints {
+			points: checkpoints.clone(),
+		});
+
+		let mut touched = vec![vec![]; 3];
+		while !sim.is_over() && sim.time() < 600. {
+			for event in sim.step() {
+				match event {
+					Event::Checkpoint(i) => touched[i].push(sim.horses()[i].pos),
+					Event::Finish(i) => assert_eq!(touched[i].len(), checkpoints.len()),
+					_ => (),
+				}
+			}
+		}
+
+		assert!(sim.winner().is_some());
+		for (pos, checkpoint) in touched
+			.iter()
+			.flat_map(|touched| touched.iter().zip(&checkpoints))
+		{
+			assert!(pos.distance(*checkpoint) < DIR_WIDTH * 3.);
+		}
+	}
+
+	#[test]
+	fn segments() {
+		// A box inside the arena, with a wall most of the way across it
+		let corners = [
+			vec2(50., 50.),
+			vec2(350., 50.),
+			vec2(350., 350.),
+			vec2(50., 350.),
+		];
+		let walls = geometry::Walls::Polygons {
+			polygons: vec![corners.to_vec()],
+		};
+		let mut segments = walls.geometry(&arena()).unwrap().segments().to_vec();
+		segments.push([vec2(50., 250.), vec2(280., 250.)]);
+
+		let mut sim = sim(1234).with_geometry(Geometry::new(segments));
+		while !sim.is_over() && sim.time() < 600. {
+			sim.step();
+			for horse in sim.horses() {
+				assert!(horse.pos.cmpgt(vec2(50., 50.)).all());
+				assert!(horse.pos.cmplt(vec2(350., 350.)).all());
+			}
+		}
+		assert!(sim.winner().is_some());
+	}
+
+	#[test]
+	fn reflect() {
+		let bounce = Bounce::Reflect {
+			restitution: 1.,
+			chaos: 0.,
+		};
+		let a = sim(1234).with_bounce(bounce).run(600., |_, _| ());
+		let b = sim(1234).with_bounce(bounce).run(600., |_, _| ());
+		let classic = sim(1234).run(600., |_, _| ());
+
+		assert!(a.winner.is_some());
+		assert_eq!(a.time.to_bits(), b.time.to_bits());
+		assert_ne!(a.time.to_bits(), classic.time.to_bits());
+	}
+
+	#[test]
+	fn mirror() {
+		let horses = vec![(vec2(30., 200.), WinData::default(), Stats::default())];
+		let mut sim =
+			Sim::new(arena(), vec2(300., 300.), horses, 1234).with_bounce(Bounce::Reflect {
+				restitution: 1.,
+				chaos: 0.,
+			});
+		sim.horses[0].dir = vec2(-1., 1.).normalize();
+
+		// A flat image wall mirrors the horse, rather than sending it back along a probe
+		while !sim.step().contains(&Event::Bounce(0)) {}
+		assert!(
+			sim.horses[0]
+				.dir
+				.abs_diff_eq(vec2(1., 1.).normalize(), 1e-4)
+		);
+	}
+
+	#[test]
+	fn twins() {
+		let horses = vec![(vec2(100., 100.), WinData::default(), Stats::default()); 2];
+		let mut sim = Sim::new(arena(), vec2(300., 300.), horses, 1234);
+
+		// Identical horses in the same spot still run into each other, but not themselves
+		let grid = sim.grid();
+		assert!(sim.overlap(0, &grid).is_some());
+		assert!(sim.overlap(1, &grid).is_some());
+		let events = sim.step();
+		assert!(events.contains(&Event::Hit(0)) && events.contains(&Event::Hit(1)));
+	}
+
+	#[test]
+	fn recorded() {
+		let mut sim = sim(1234);
+		let pos = sim
+			.horses
+			.iter()
+			.map(|horse| horse.pos - vec2(3., 0.))
+			.collect::<Vec<_>>();
+		sim.step_recorded(&pos, &[], &[], &[], &[]);
+
+		assert!(sim.horses.iter().all(|horse| horse.dir == vec2(-1., 0.)));
+	}
+
+	#[test]
+	fn bumper() {
+		let bumper = Object {
+			pos: vec2(60., 100.),
+			radius: 20.,
+			path: vec![],
+			speed: 0.,
+			effect: Effect::Bumper { factor: 2. },
+		};
+		let mut sim = sim(1234)
+			.with_bounce(Bounce::Reflect {
+				restitution: 1.,
+				chaos: 0.,
+			})
+			.with_objects(vec![bumper]);
+		sim.horses[0].dir = vec2(1., 0.);
+
+		// Horses already moving away from a bumper are not bounced or sped up by it
+		for _ in 0..3 {
+			assert!(!sim.step().contains(&Event::Object(0, 0)));
+		}
+		assert!(sim.horses[0].speed < sim.horses[0].stats.top_speed);
+	}
+
+	#[test]
+	fn teleporter() {
+		let teleporter = |pos, to| Object {
+			pos,
+			radius: 20.,
+			path: vec![],
+			speed: 0.,
+			effect: Effect::Teleporter { to },
+		};
+		let mut sim = sim(1234).with_objects(vec![
+			teleporter(vec2(100., 100.), vec2(300., 100.)),
+			teleporter(vec2(300., 100.), vec2(100., 100.)),
+		]);
+
+		assert!(sim.step().contains(&Event::Object(0, 0)));
+		assert_eq!(sim.horses()[0].pos, vec2(300., 100.));
+		assert!(!sim.step().contains(&Event::Object(0, 1)));
+	}
+
+	#[test]
+	fn survival() {
+		let hazards = vec![goal::Hazard {
+			pos: vec2(200., 200.),
+			radius: 60.,
+		}];
+		let outcome = sim(1234)
+			.with_finishers(0)
+			.with_goal(Goal::Survival { hazards })
+			.run(600., |sim, events| {
+				for event in events {
+					if let Event::Out(i) = event {
+						assert!(sim.is_out(*i));
+					}
+				}
+			});
+
+		// Only the last horse left finishes
+		assert_eq!(outcome.finished.len(), 1);
+		assert!(outcome.winner.is_some());
+	}
+}
@@ -0,0 +1,98 @@
+use macroquad::{prelude::*, rand::RandGenerator};
+use serde::{Deserialize, Serialize};
+
+use super::horse::Horse;
+
+/// How horses bounce off of things. Declared as `[bounce]` in a race file, with `kind` set to one of the variants.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Bounce {
+	/// Turn away from what was hit in a mostly random direction, at a random speed between 1 and 2
+	#[default]
+	Classic,
+	/// Reflect off of what was hit, and push other horses along
+	Reflect {
+		/// How much speed is kept after every bounce, from 0 to 1
+		#[serde(default = "Bounce::default_restitution")]
+		restitution: f32,
+		/// How far every bounce is randomly turned, in radians
+		#[serde(default)]
+		chaos: f32,
+	},
+}
+
+impl Bounce {
+	fn default_restitution() -> f32 {
+		1.
+	}
+
+	pub fn is_classic(self) -> bool {
+		self == Self::Classic
+	}
+}
+
+/// Mirrors a direction off of a surface facing `normal`.
+pub fn reflect(dir: Vec2, normal: Vec2) -> Vec2 {
+	dir - 2. * dir.dot(normal) * normal
+}
+
+/// Turns a direction by a random angle of up to `chaos` radians either way.
+pub fn jitter(dir: Vec2, chaos: f32, rng: &RandGenerator) -> Vec2 {
+	if chaos <= 0. {
+		return dir;
+	}
+	Vec2::from_angle(rng.gen_range(-chaos, chaos)).rotate(dir)
+}
+
+/// Two horses pushing off of each other, as if they were balls as heavy as they are big.
+/// `normal` points from `b` to `a`. Horses that are already moving apart are left alone.
+pub fn exchange(a: &mut Horse, b: &mut Horse, normal: Vec2, restitution: f32) -> bool {
+	let (va, vb) = (a.dir * a.speed, b.dir * b.speed);
+	let closing = (va - vb).dot(normal);
+	if closing >= 0. {
+		return false;
+	}
+
+	let (ma, mb) = (a.stats.size.powi(2), b.stats.size.powi(2));
+	let impulse = -(1. + restitution) * closing / (1. / ma + 1. / mb);
+
+	for (horse, velocity) in [
+		(a, va + normal * impulse / ma),
+		(b, vb - normal * impulse / mb),
+	] {
+		horse.speed = velocity.length();
+		horse.dir = velocity.normalize_or(horse.dir);
+	}
+	true
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::data::{Stats, WinData};
+
+	#[test]
+	fn reflects() {
+		assert_eq!(reflect(vec2(1., 1.), vec2(0., -1.)), vec2(1., -1.));
+		assert_eq!(reflect(vec2(-1., 0.), vec2(1., 0.)), vec2(1., 0.));
+	}
+
+	#[test]
+	fn head_on() {
+		let rng = RandGenerator::new();
+		let horse = |id, pos, dir| {
+			let mut horse = Horse::new(id, pos, WinData::default(), Stats::default(), &rng);
+			horse.dir = dir;
+			horse.speed = 2.;
+			horse
+		};
+		let mut a = horse(0, vec2(0., 0.), vec2(1., 0.));
+		let mut b = horse(1, vec2(10., 0.), vec2(-1., 0.));
+
+		// Equal horses swap velocities when nothing is lost
+		assert!(exchange(&mut a, &mut b, vec2(-1., 0.), 1.));
+		assert_eq!((a.dir, a.speed), (vec2(-1., 0.), 2.));
+		assert_eq!((b.dir, b.speed), (vec2(1., 0.), 2.));
+		assert!(!exchange(&mut a, &mut b, vec2(-1., 0.), 1.));
+	}
+}
@@ -0,0 +1,134 @@
+use std::{
+	ops::Range,
+	sync::atomic::{AtomicUsize, Ordering},
+	thread,
+};
+
+use super::Sim;
+
+/// How likely each horse is to win, found by simulating many races.
+pub struct Estimate {
+	pub horses: Vec<HorseEstimate>,
+	/// How many races were simulated
+	pub races: usize,
+}
+
+pub struct HorseEstimate {
+	pub wins: usize,
+	/// The median time of the races this horse won, or `None` if it never won
+	pub median_time: Option<f32>,
+}
+
+impl Estimate {
+	/// Simulates a race for every seed, spread across every available thread.
+	/// Races that no horse wins within `limit` seconds count against every horse.
+	/// The same seeds always give the same estimate, no matter how many threads there are.
+	/// `sim` sets up the race for a seed, with the same `horses` every time.
+	/// `done` counts every race as it finishes, so progress can be shown.
+	pub fn new(
+		horses: usize,
+		seeds: Range<u64>,
+		limit: f32,
+		done: &AtomicUsize,
+		sim: impl Fn(u64) -> Sim + Sync,
+	) -> Self {
+		let seeds = seeds.collect::<Vec<_>>();
+		let threads = thread::available_parallelism().map_or(1, usize::from);
+		let chunk = seeds.len().div_ceil(threads).max(1);
+
+		let race = |seed: u64| {
+			let outcome = sim(seed).run(limit, |_, _| ());
+			done.fetch_add(1, Ordering::Release);
+			outcome
+				.finished
+				.first()
+				.map(|finish| (finish.horse, finish.time))
+		};
+
+		// Every chunk is joined in order, so results do not depend on which thread finishes first
+		let outcomes = thread::scope(|scope| {
+			seeds
+				.chunks(chunk)
+				.map(|seeds| {
+					scope.spawn(|| seeds.iter().map(|seed| race(*seed)).collect::<Vec<_>>())
+				})
+				.collect::<Vec<_>>()
+				.into_iter()
+				.flat_map(|handle| handle.join().expect("Simulation thread panicked"))
+				.collect::<Vec<_>>()
+		});
+
+		let mut times = vec![vec![]; horses];
+		for (winner, time) in outcomes.iter().flatten() {
+			times[*winner].push(*time);
+		}
+
+		Self {
+			horses: times.into_iter().map(HorseEstimate::new).collect(),
+			races: outcomes.len(),
+		}
+	}
+}
+
+impl HorseEstimate {
+	fn new(mut times: Vec<f32>) -> Self {
+		times.sort_by(f32::total_cmp);
+		let median_time = match times.len() {
+			0 => None,
+			len if len % 2 == 0 => Some(f32::midpoint(times[len / 2 - 1], times[len / 2])),
+			len => Some(times[len / 2]),
+		};
+
+		Self {
+			wins: times.len(),
+			median_time,
+		}
+	}
+
+	/// How likely this horse is to win, from 0 to 1.
+	pub fn chance(&self, races: usize) -> f32 {
+		if races == 0 {
+			return 0.;
+		}
+		self.wins as f32 / races as f32
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use macroquad::prelude::*;
+
+	use super::*;
+	use crate::data::{Stats, WinData};
+
+	#[test]
+	fn stable() {
+		let arena = Arc::new(crate::sim::tests::arena());
+		let horses = [vec2(100., 100.), vec2(200., 100.), vec2(100., 200.)];
+		let estimate = || {
+			Estimate::new(horses.len(), 0..20, 600., &AtomicUsize::new(0), |seed| {
+				let horses = horses
+					.iter()
+					.map(|pos| (*pos, WinData::default(), Stats::default()))
+					.collect();
+				Sim::new(arena.clone(), vec2(300., 300.), horses, seed)
+			})
+		};
+
+		let (a, b) = (estimate(), estimate());
+		assert_eq!(a.races, 20);
+		assert_eq!(
+			a.horses.iter().map(|horse| horse.wins).sum::<usize>(),
+			a.races
+		);
+		for (a, b) in a.horses.iter().zip(&b.horses) {
+			assert_eq!(a.wins, b.wins);
+			assert_eq!(
+				a.median_time.map(f32::to_bits),
+				b.median_time.map(f32::to_bits)
+			);
+		}
+	}
+}
@@ -0,0 +1,199 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use macroquad::prelude::*;
+
+use super::horse::is_wall_cell;
+
+/// The size of each cell of the field, in pixels.
+/// Anything smaller than a horse is fine, and larger cells are much faster to compute.
+const CELL: u32 = 4;
+/// The cost of moving to the next cell over, or diagonally, in tenths of a cell.
+const STRAIGHT: u32 = 10;
+const DIAGONAL: u32 = 14;
+
+/// How far every point in the arena is from the carrots, walking around walls.
+pub struct DistanceField {
+	width: u32,
+	height: u32,
+	/// In tenths of a cell, or `u32::MAX` if the carrots cannot be reached
+	distances: Vec<u32>,
+}
+
+impl DistanceField {
+	/// Walks outwards from the goal, through every cell without any wall in it.
+	pub fn new(arena: &Image, goal: Vec2) -> Self {
+		let width = (arena.width() as u32).div_ceil(CELL);
+		let height = (arena.height() as u32).div_ceil(CELL);
+		let open = (0..width * height)
+			.map(|i| !is_wall_cell(arena, uvec2(i % width, i / width) * CELL, CELL))
+			.collect::<Vec<_>>();
+
+		let mut field = Self {
+			width,
+			height,
+			distances: vec![u32::MAX; open.len()],
+		};
+		let Some(start) = field.index(goal) else {
+			return field;
+		};
+
+		let mut queue = BinaryHeap::from([Reverse((0, start))]);
+		field.distances[start] = 0;
+
+		while let Some(Reverse((distance, i))) = queue.pop() {
+			if distance > field.distances[i] {
+				continue;
+			}
+
+			let (x, y) = field.coords(i);
+			for (dx, dy) in [
+				(-1, 0),
+				(1, 0),
+				(0, -1),
+				(0, 1),
+				(-1, -1),
+				(1, -1),
+				(-1, 1),
+				(1, 1),
+			] {
+				let Some(next) = field.cell(x + dx, y + dy).filter(|next| open[*next]) else {
+					continue;
+				};
+
+				let cost = if dx != 0 && dy != 0 {
+					// Diagonals cannot cut through the corner of a wall
+					let corners = [field.cell(x + dx, y), field.cell(x, y + dy)];
+					if !corners.iter().flatten().all(|corner| open[*corner]) {
+						continue;
+					}
+					DIAGONAL
+				} else {
+					STRAIGHT
+				};
+
+				let distance = distance + cost;
+				if distance < field.distances[next] {
+					field.distances[next] = distance;
+					queue.push(Reverse((distance, next)));
+				}
+			}
+		}
+
+		field
+	}
+
+	/// How far a point is from the carrots in pixels, or `None` if the carrots cannot be reached from it.
+	/// Points inside walls use the closest open cell next to them, since horses are often slightly inside one.
+	pub fn distance(&self, pos: Vec2) -> Option<f32> {
+		let i = self.index(pos)?;
+		let (x, y) = self.coords(i);
+
+		let distance = if self.distances[i] == u32::MAX {
+			(-1..=1)
+				.flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+				.filter_map(|(dx, dy)| self.cell(x + dx, y + dy))
+				.map(|next| self.distances[next].saturating_add(STRAIGHT))
+				.min()?
+		} else {
+			self.distances[i]
+		};
+
+		(distance != u32::MAX).then(|| distance as f32 / STRAIGHT as f32 * CELL as f32)
+	}
+
+	/// Draws the field as an image, one pixel per cell.
+	/// Close cells are green and far ones are red. Cells the carrots cannot be reached from are left clear.
+	pub fn image(&self) -> Image {
+		let mut image = Image::gen_image_color(self.width as u16, self.height as u16, BLANK);
+		let max = self
+			.distances
+			.iter()
+			.filter(|distance| **distance != u32::MAX)
+			.max()
+			.copied()
+			.unwrap_or(1)
+			.max(1);
+
+		for (i, distance) in self.distances.iter().enumerate() {
+			if *distance == u32::MAX {
+				continue;
+			}
+
+			let far = *distance as f32 / max as f32;
+			let color = Color::new(far, 1. - far, 0., 0.5);
+			image.set_pixel(i as u32 % self.width, i as u32 / self.width, color);
+		}
+		image
+	}
+
+	fn index(&self, pos: Vec2) -> Option<usize> {
+		if pos.x < 0. || pos.y < 0. {
+			return None;
+		}
+		let cell = pos.as_uvec2() / CELL;
+		self.cell(cell.x.cast_signed(), cell.y.cast_signed())
+	}
+
+	fn coords(&self, i: usize) -> (i32, i32) {
+		let i = i as u32;
+		(
+			(i % self.width).cast_signed(),
+			(i / self.width).cast_signed(),
+		)
+	}
+
+	fn cell(&self, x: i32, y: i32) -> Option<usize> {
+		let inside = x >= 0 && y >= 0 && (x as u32) < self.width && (y as u32) < self.height;
+		inside.then(|| (y as u32 * self.width + x as u32) as usize)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn walks_around_walls() {
+		// A wall across most of the arena, with a gap at the bottom
+		let mut arena = Image::gen_image_color(100, 100, BLANK);
+		for y in 0..80 {
+			for x in 48..52 {
+				arena.set_pixel(x, y, WHITE);
+			}
+		}
+		let field = DistanceField::new(&arena, vec2(90., 10.));
+
+		let around = field.distance(vec2(10., 10.)).unwrap();
+		assert!(
+			around > 140.,
+			"{around} should be further than around the wall"
+		);
+		assert!(field.distance(vec2(80., 10.)).unwrap() < 12.);
+		assert_eq!(field.distance(vec2(200., 10.)), None);
+	}
+
+	#[test]
+	fn unreachable() {
+		let mut arena = Image::gen_image_color(100, 100, BLANK);
+		for i in 0..100 {
+			arena.set_pixel(50, i, WHITE);
+			arena.set_pixel(51, i, WHITE);
+			arena.set_pixel(52, i, WHITE);
+			arena.set_pixel(53, i, WHITE);
+		}
+		let field = DistanceField::new(&arena, vec2(90., 10.));
+
+		assert_eq!(field.distance(vec2(10., 10.)), None);
+	}
+
+	#[test]
+	fn thin_wall() {
+		let mut arena = Image::gen_image_color(100, 100, BLANK);
+		for i in 0..100 {
+			arena.set_pixel(48, i, WHITE);
+		}
+		let field = DistanceField::new(&arena, vec2(90., 10.));
+
+		assert_eq!(field.distance(vec2(10., 10.)), None);
+	}
+}
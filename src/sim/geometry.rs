@@ -0,0 +1,266 @@
+use hashbrown::HashMap;
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::horse::is_wall_cell;
+
+/// The size of each bucket segments are sorted into, in pixels.
+/// Horses only check the segments in the buckets they overlap.
+const BUCKET: f32 = 64.;
+/// The size of each cell the foreground is traced in, in pixels.
+const TRACE_CELL: u32 = 4;
+/// How far from a segment pixels are filled in when drawing it as an image.
+/// Wide enough that a [`super::field::DistanceField`] cannot slip between them.
+const RASTER_WIDTH: i32 = 2;
+
+/// What horses bounce off of. Declared as `[walls]` in a race file, with `kind` set to one of the variants.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Walls {
+	/// The opaque pixels of the foreground, probed at eight points around every horse.
+	/// Classic bounces probe other horses the same way, so existing races play out as they always have
+	#[default]
+	Image,
+	/// Lines from one point to another
+	Segments { segments: Vec<[Vec2; 2]> },
+	/// Closed shapes, each a list of corners
+	Polygons { polygons: Vec<Vec<Vec2>> },
+	/// The edges of the opaque pixels of the foreground
+	Traced,
+}
+
+/// Walls made of line segments, which horses collide with as circles.
+pub struct Geometry {
+	segments: Vec<[Vec2; 2]>,
+	/// The index of every segment that passes through each bucket
+	buckets: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl Walls {
+	/// The segments to collide with, or `None` if horses should probe the image instead.
+	pub fn geometry(&self, arena: &Image) -> Option<Geometry> {
+		let segments = match self {
+			Self::Image => return None,
+			Self::Segments { segments } => segments.clone(),
+			Self::Polygons { polygons } => polygons
+				.iter()
+				.flat_map(|corners| {
+					corners
+						.iter()
+						.zip(corners.iter().cycle().skip(1))
+						.map(|(a, b)| [*a, *b])
+				})
+				.collect(),
+			Self::Traced => trace(arena),
+		};
+		Some(Geometry::new(segments))
+	}
+}
+
+impl Geometry {
+	pub fn new(segments: Vec<[Vec2; 2]>) -> Self {
+		let mut buckets = HashMap::<_, Vec<_>>::new();
+		for (i, [a, b]) in segments.iter().enumerate() {
+			let (min, max) = (bucket(a.min(*b)), bucket(a.max(*b)));
+			for y in min.1..=max.1 {
+				for x in min.0..=max.0 {
+					buckets.entry((x, y)).or_default().push(i);
+				}
+			}
+		}
+
+		Self { segments, buckets }
+	}
+
+	pub fn segments(&self) -> &[[Vec2; 2]] {
+		&self.segments
+	}
+
+	/// The direction away from every segment a circle overlaps, weighted by how far into them it is.
+	/// `None` if it does not overlap any.
+	pub fn contact(&self, center: Vec2, radius: f32) -> Option<Vec2> {
+		let (min, max) = (bucket(center - radius), bucket(center + radius));
+		let mut nearby = (min.1..=max.1)
+			.flat_map(|y| (min.0..=max.0).map(move |x| (x, y)))
+			.filter_map(|bucket| self.buckets.get(&bucket))
+			.flatten()
+			.copied()
+			.collect::<Vec<_>>();
+		// Sorted so the normal is added up in the same order every time
+		nearby.sort_unstable();
+		nearby.dedup();
+
+		let mut normal = Vec2::ZERO;
+		let mut touching = false;
+		for [a, b] in nearby.into_iter().map(|i| self.segments[i]) {
+			let closest = closest_point(a, b, center);
+			let distance = center.distance(closest);
+			if distance >= radius {
+				continue;
+			}
+
+			touching = true;
+			let away = (center - closest)
+				.try_normalize()
+				.unwrap_or_else(|| (b - a).perp().normalize_or_zero());
+			normal += away * (radius - distance);
+		}
+
+		touching.then(|| normal.normalize_or_zero())
+	}
+
+	/// Draws the segments as walls on a clear image of the given size.
+	/// Lets code that works on images, such as the distance field, use segments too.
+	pub fn image(&self, width: u16, height: u16) -> Image {
+		let mut image = Image::gen_image_color(width, height, BLANK);
+		for [a, b] in &self.segments {
+			let steps = a.distance(*b).ceil().max(1.);
+			for step in 0..=steps as u32 {
+				let point = a.lerp(*b, step as f32 / steps).as_ivec2();
+				for dy in -RASTER_WIDTH..=RASTER_WIDTH {
+					for dx in -RASTER_WIDTH..=RASTER_WIDTH {
+						let (x, y) = (point.x + dx, point.y + dy);
+						if x >= 0 && y >= 0 && x < i32::from(width) && y < i32::from(height) {
+							image.set_pixel(x as u32, y as u32, WHITE);
+						}
+					}
+				}
+			}
+		}
+		image
+	}
+}
+
+fn bucket(pos: Vec2) -> (i32, i32) {
+	let bucket = (pos / BUCKET).floor();
+	(bucket.x as i32, bucket.y as i32)
+}
+
+fn closest_point(a: Vec2, b: Vec2, point: Vec2) -> Vec2 {
+	let line = b - a;
+	let length = line.length_squared();
+	if length <= f32::EPSILON {
+		return a;
+	}
+	a + line * ((point - a).dot(line) / length).clamp(0., 1.)
+}
+
+/// Finds every edge between wall and open cells of the image, joining edges in a straight line together.
+/// A cell is a wall if any pixel in it is, so thin walls are traced as a whole cell wide.
+/// Everything outside of the image counts as a wall, so the arena is always closed.
+fn trace(arena: &Image) -> Vec<[Vec2; 2]> {
+	let width = (arena.width() as u32).div_ceil(TRACE_CELL).cast_signed();
+	let height = (arena.height() as u32).div_ceil(TRACE_CELL).cast_signed();
+	let walls = (0..width * height)
+		.map(|i| {
+			let corner = ivec2(i % width, i / width).as_uvec2() * TRACE_CELL;
+			is_wall_cell(arena, corner, TRACE_CELL)
+		})
+		.collect::<Vec<_>>();
+	let wall = |x: i32, y: i32| {
+		x < 0 || y < 0 || x >= width || y >= height || walls[(y * width + x) as usize]
+	};
+
+	let mut segments = vec![];
+	let cell = TRACE_CELL as f32;
+	let mut push = |start: Option<i32>, end: i32, line: i32, horizontal: bool| {
+		if let Some(start) = start {
+			let (a, b) = if horizontal {
+				(
+					vec2(start as f32, line as f32),
+					vec2(end as f32, line as f32),
+				)
+			} else {
+				(
+					vec2(line as f32, start as f32),
+					vec2(line as f32, end as f32),
+				)
+			};
+			segments.push([a * cell, b * cell]);
+		}
+	};
+
+	// Edges along the top of every row of cells
+	for y in 0..=height {
+		let mut start = None;
+		for x in 0..=width {
+			let edge = x < width && wall(x, y - 1) != wall(x, y);
+			match (edge, start) {
+				(true, None) => start = Some(x),
+				(false, Some(_)) => {
+					push(start, x, y, true);
+					start = None;
+				}
+				_ => (),
+			}
+		}
+	}
+	// Edges along the left of every column of cells
+	for x in 0..=width {
+		let mut start = None;
+		for y in 0..=height {
+			let edge = y < height && wall(x - 1, y) != wall(x, y);
+			match (edge, start) {
+				(true, None) => start = Some(y),
+				(false, Some(_)) => {
+					push(start, y, x, false);
+					start = None;
+				}
+				_ => (),
+			}
+		}
+	}
+
+	segments
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn contact() {
+		let geometry = Geometry::new(vec![[vec2(0., 100.), vec2(200., 100.)]]);
+
+		assert_eq!(geometry.contact(vec2(50., 80.), 30.), Some(vec2(0., -1.)));
+		assert_eq!(geometry.contact(vec2(50., 130.), 40.), Some(vec2(0., 1.)));
+		assert_eq!(geometry.contact(vec2(50., 50.), 30.), None);
+		// Past the end of the segment, pushed away from its corner
+		let corner = geometry.contact(vec2(220., 90.), 30.).unwrap();
+		assert!(corner.x > 0. && corner.y < 0.);
+	}
+
+	#[test]
+	fn traced() {
+		let mut arena = Image::gen_image_color(40, 40, BLANK);
+		for y in 0..40 {
+			for x in 20..40 {
+				arena.set_pixel(x, y, WHITE);
+			}
+		}
+		let geometry = Walls::Traced.geometry(&arena).unwrap();
+
+		// The outside of the image, minus where the wall covers it, and the edge of the wall
+		assert_eq!(geometry.segments().len(), 4);
+		assert!(
+			geometry
+				.segments()
+				.contains(&[vec2(20., 0.), vec2(20., 40.)])
+		);
+		assert_eq!(geometry.contact(vec2(15., 20.), 10.), Some(vec2(-1., 0.)));
+	}
+
+	#[test]
+	fn thin() {
+		let mut arena = Image::gen_image_color(40, 40, BLANK);
+		for y in 0..40 {
+			arena.set_pixel(21, y, WHITE);
+		}
+		let geometry = Walls::Traced.geometry(&arena).unwrap();
+
+		// Both sides of the cell the wall is in, on top of the outside of the image
+		assert!(geometry.segments().len() > 4);
+		assert_eq!(geometry.contact(vec2(15., 20.), 10.), Some(vec2(-1., 0.)));
+		assert_eq!(geometry.contact(vec2(30., 20.), 10.), Some(vec2(1., 0.)));
+	}
+}
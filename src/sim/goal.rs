@@ -0,0 +1,142 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::horse::Horse;
+
+/// How a race is won. Declared as `[goal]` in a race file, with `kind` set to one of the variants.
+/// Races without one are won by reaching the carrots.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Goal {
+	#[default]
+	Carrots,
+	/// Reach any of these points, or the carrots
+	Any { points: Vec<Vec2> },
+	/// Touch every checkpoint in order, then reach the carrots
+	Checkpoints { points: Vec<Vec2> },
+	/// Touch every checkpoint in order, this many times over, then reach the carrots
+	Laps { points: Vec<Vec2>, laps: u32 },
+	/// Horses that touch a hazard are out of the race, and the last one left wins
+	Survival { hazards: Vec<Hazard> },
+}
+
+/// A circle that knocks horses out of a [`Goal::Survival`] race.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Hazard {
+	pub pos: Vec2,
+	/// How close the center of a horse can get before it is out, in pixels
+	pub radius: f32,
+}
+
+/// What a horse did on a tick, according to the goal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reached {
+	Checkpoint,
+	Finish,
+	Hazard,
+}
+
+impl Goal {
+	/// How many checkpoints have to be touched before the carrots count.
+	pub fn checkpoints(&self) -> usize {
+		match self {
+			Self::Checkpoints { points } => points.len(),
+			Self::Laps { points, laps } => points.len() * *laps as usize,
+			_ => 0,
+		}
+	}
+
+	/// Where a checkpoint is. Laps go around the same points again.
+	pub fn checkpoint(&self, i: usize) -> Option<Vec2> {
+		match self {
+			Self::Checkpoints { points } | Self::Laps { points, .. } if i < self.checkpoints() => {
+				Some(points[i % points.len()])
+			}
+			_ => None,
+		}
+	}
+
+	/// If horses have to reach the carrots to finish.
+	pub fn uses_carrots(&self) -> bool {
+		!matches!(self, Self::Survival { .. })
+	}
+
+	/// What a horse that has touched `progress` checkpoints has reached, if anything.
+	pub fn reached(&self, horse: &Horse, progress: usize, carrots: Vec2) -> Option<Reached> {
+		match self {
+			Self::Carrots => horse.collision_carrots(carrots).then_some(Reached::Finish),
+			Self::Any { points } => points
+				.iter()
+				.chain([&carrots])
+				.any(|point| horse.collision_carrots(*point))
+				.then_some(Reached::Finish),
+			Self::Checkpoints { .. } | Self::Laps { .. } => match self.checkpoint(progress) {
+				Some(checkpoint) => horse
+					.collision_carrots(checkpoint)
+					.then_some(Reached::Checkpoint),
+				None => horse.collision_carrots(carrots).then_some(Reached::Finish),
+			},
+			Self::Survival { hazards } => hazards
+				.iter()
+				.any(|hazard| horse.pos.distance(hazard.pos) <= hazard.radius)
+				.then_some(Reached::Hazard),
+		}
+	}
+
+	/// Every position the goal adds to the arena, in the order they are declared.
+	pub fn points(&self) -> Vec<Vec2> {
+		match self {
+			Self::Carrots => vec![],
+			Self::Any { points } | Self::Checkpoints { points } | Self::Laps { points, .. } => {
+				points.clone()
+			}
+			Self::Survival { hazards } => hazards.iter().map(|hazard| hazard.pos).collect(),
+		}
+	}
+
+	/// Gets a position to move it, or `None` if there are not that many.
+	pub fn point_mut(&mut self, i: usize) -> Option<&mut Vec2> {
+		match self {
+			Self::Carrots => None,
+			Self::Any { points } | Self::Checkpoints { points } | Self::Laps { points, .. } => {
+				points.get_mut(i)
+			}
+			Self::Survival { hazards } => hazards.get_mut(i).map(|hazard| &mut hazard.pos),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn laps() {
+		let goal = Goal::Laps {
+			points: vec![vec2(1., 1.), vec2(2., 2.)],
+			laps: 2,
+		};
+
+		assert_eq!(goal.checkpoints(), 4);
+		assert_eq!(goal.checkpoint(2), Some(vec2(1., 1.)));
+		assert_eq!(goal.checkpoint(3), Some(vec2(2., 2.)));
+		assert_eq!(goal.checkpoint(4), None);
+	}
+
+	#[test]
+	fn from_toml() {
+		let goal: Goal =
+			toml::from_str("kind = \"survival\"\nhazards = [ { pos = [10, 20], radius = 5 } ]\n")
+				.unwrap();
+
+		assert_eq!(
+			goal,
+			Goal::Survival {
+				hazards: vec![Hazard {
+					pos: vec2(10., 20.),
+					radius: 5.
+				}]
+			}
+		);
+	}
+}
@@ -0,0 +1,65 @@
+use hashbrown::HashMap;
+use macroquad::prelude::*;
+
+/// Horses sorted into square cells by where they are, so collisions only check the horses nearby.
+pub struct Grid {
+	/// How wide each cell is, in pixels, which is also the furthest apart two horses can collide from
+	size: f32,
+	/// The index of every horse in each cell
+	cells: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl Grid {
+	/// Sorts horses into cells `size` pixels wide.
+	/// `size` should be the furthest apart two horses can collide from, so lookups only need the cells next to a horse.
+	pub fn new(size: f32, horses: impl IntoIterator<Item = (usize, Vec2)>) -> Self {
+		let mut grid = Self {
+			size: size.max(1.),
+			cells: HashMap::new(),
+		};
+		for (i, pos) in horses {
+			grid.cells.entry(grid.cell(pos)).or_default().push(i);
+		}
+		grid
+	}
+
+	/// Every horse in the cells within a cell of `pos`, which includes every horse close enough to collide with it.
+	/// Sorted by index, and some of them may be further away.
+	pub fn near(&self, pos: Vec2) -> Vec<usize> {
+		let (min, max) = (self.cell(pos - self.size), self.cell(pos + self.size));
+		let mut near = (min.1..=max.1)
+			.flat_map(|y| (min.0..=max.0).map(move |x| (x, y)))
+			.filter_map(|cell| self.cells.get(&cell))
+			.flatten()
+			.copied()
+			.collect::<Vec<_>>();
+		// Sorted so collisions are checked in the same order as without a grid
+		near.sort_unstable();
+		near
+	}
+
+	fn cell(&self, pos: Vec2) -> (i32, i32) {
+		let cell = (pos / self.size).floor();
+		(cell.x as i32, cell.y as i32)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn near() {
+		let horses = [
+			vec2(10., 10.),
+			vec2(500., 500.),
+			vec2(60., 20.),
+			vec2(-30., 5.),
+		];
+		let grid = Grid::new(50., horses.into_iter().enumerate());
+
+		assert_eq!(grid.near(vec2(10., 10.)), [0, 2, 3]);
+		assert_eq!(grid.near(vec2(480., 520.)), [1]);
+		assert!(grid.near(vec2(1000., 1000.)).is_empty());
+	}
+}
@@ -0,0 +1,279 @@
+use core::f32;
+
+use macroquad::{prelude::*, rand::RandGenerator};
+
+use super::bounce::{Bounce, jitter, reflect};
+
+use crate::{
+	data::{Stats, WinData},
+	dirs,
+	utils::Dirs,
+};
+
+pub type Collisions = u8;
+
+pub const NO_COLLISION: Collisions = 0;
+pub const DIR_WIDTH: f32 = 36.;
+/// Pixels in the arena more opaque than this are walls.
+pub const WALL_ALPHA: f32 = 0.75;
+
+#[rustfmt::skip]
+pub const DIRS: Dirs<8> = dirs![
+	( normal(DIR_WIDTH),-normal(DIR_WIDTH)),
+	(-normal(DIR_WIDTH), normal(DIR_WIDTH)),
+	( normal(DIR_WIDTH), normal(DIR_WIDTH)),
+	(-normal(DIR_WIDTH),-normal(DIR_WIDTH)),
+	( 0.,-DIR_WIDTH),
+	( 0., DIR_WIDTH),
+	(-DIR_WIDTH, 0.),
+	( DIR_WIDTH, 0.),
+];
+
+#[derive(Clone)]
+pub struct Horse {
+	/// Which horse this is, from its place in the race file's list of horses
+	pub id: usize,
+	pub pos: Vec2,
+	pub dir: Vec2,
+	pub speed: f32,
+	pub win_data: WinData,
+	pub stats: Stats,
+}
+
+impl Horse {
+	pub fn new(id: usize, pos: Vec2, win_data: WinData, stats: Stats, rng: &RandGenerator) -> Self {
+		Self {
+			id,
+			pos,
+			dir: vec2(rng.gen_range(-1., 1.), rng.gen_range(-1., 1.)).normalize(),
+			speed: 1.,
+			win_data,
+			stats,
+		}
+	}
+
+	/// Moves the horse forward by one tick.
+	/// Ticks are always [`super::TICK`] long, so no frame time scaling is needed.
+	pub fn update(&mut self) {
+		self.pos += self.dir * self.speed * 2.;
+
+		if self.speed < self.stats.top_speed {
+			self.speed += self.stats.acceleration;
+		}
+	}
+
+	pub fn collision_wall(&self, image: &Image) -> Collisions {
+		let mut collisions = NO_COLLISION;
+
+		for (i, dir) in self.dirs().enumerate() {
+			if is_wall(image, self.pos + dir) {
+				collisions |= 1 << i;
+			}
+		}
+
+		collisions
+	}
+
+	/// Which sides of the horse are touching other horses.
+	pub fn collision_honses<'a>(&self, honses: impl IntoIterator<Item = &'a Horse>) -> Collisions {
+		let mut collisions = NO_COLLISION;
+
+		for honse in honses {
+			if honse.id == self.id {
+				continue;
+			}
+			let reach = f32::midpoint(self.stats.size, honse.stats.size);
+
+			for (i, dir) in self.dirs().enumerate() {
+				let bit = (honse.pos.distance(self.pos + dir) <= reach) as u8;
+				collisions |= bit << i;
+			}
+		}
+
+		collisions
+	}
+
+	/// Which sides of the horse are inside a circle.
+	pub fn collision_circle(&self, center: Vec2, radius: f32) -> Collisions {
+		let mut collisions = NO_COLLISION;
+
+		for (i, dir) in self.dirs().enumerate() {
+			if center.distance(self.pos + dir) <= radius {
+				collisions |= 1 << i;
+			}
+		}
+
+		collisions
+	}
+
+	pub fn collision_carrots(&self, carrots: Vec2) -> bool {
+		let mut collision = false;
+		for dir in self.dirs() {
+			let pos = self.pos + dir;
+			collision |= carrots.distance(pos) <= self.stats.size;
+		}
+		collision
+	}
+
+	/// Turns away from whatever the horse collided with.
+	/// `away` is [`DIR_WIDTH`] long, pointing from what was hit towards the horse.
+	/// Reflecting mirrors the direction about it, so it should be a real normal rather than a single probe.
+	/// Lucky horses are steered towards the carrots.
+	pub fn bounce(&mut self, away: Vec2, carrots: Vec2, bounce: Bounce, rng: &RandGenerator) {
+		match bounce {
+			Bounce::Classic => {
+				let mut new_dir = -away;
+
+				new_dir.x += rng.gen_range(-24., 24.);
+				new_dir.y += rng.gen_range(-24., 24.);
+
+				self.dir = new_dir.normalize() * -1.;
+				self.speed = rng.gen_range(1., 2.) * self.stats.elasticity;
+			}
+			Bounce::Reflect { restitution, chaos } => {
+				let reflected = reflect(self.dir, away.normalize_or(-self.dir));
+				self.dir = jitter(reflected, chaos, rng).normalize_or(self.dir);
+				self.speed *= restitution * self.stats.elasticity;
+			}
+		}
+
+		if self.stats.luck > 0. {
+			let towards = (carrots - self.pos).normalize_or(self.dir);
+			self.dir = self
+				.dir
+				.lerp(towards, self.stats.luck)
+				.normalize_or(self.dir);
+		}
+	}
+
+	/// How far from its center the horse collides with things, when it is a circle.
+	pub fn radius(&self) -> f32 {
+		self.stats.size
+	}
+
+	/// The points around the horse that are checked for collisions, scaled to its size.
+	fn dirs(&self) -> impl Iterator<Item = Vec2> {
+		let scale = self.stats.size / DIR_WIDTH;
+		DIRS.into_iter().map(move |dir| dir * scale)
+	}
+}
+
+/// Which way to bounce off of the collisions, from the last point around the horse that collided.
+/// `None` if there are none.
+pub fn away(collisions: Collisions) -> Option<Vec2> {
+	(0..u8::BITS)
+		.rev()
+		.find(|i| collisions & (1 << i) != 0)
+		.map(|i| -DIRS[i as usize])
+}
+
+/// The direction away from every point around the horse that collided, added up and normalized.
+/// `None` if there are none, and zero if they cancel out.
+pub fn probe_normal(collisions: Collisions) -> Option<Vec2> {
+	(collisions != NO_COLLISION).then(|| {
+		DIRS.into_iter()
+			.enumerate()
+			.filter(|(i, _)| collisions & (1 << i) != 0)
+			.map(|(_, dir)| -dir)
+			.sum::<Vec2>()
+			.normalize_or_zero()
+	})
+}
+
+/// Checks if a position in the arena is a wall.
+/// Anything outside of the arena counts as a wall.
+pub fn is_wall(image: &Image, pos: Vec2) -> bool {
+	// Casting would turn negative positions into the first row or column
+	if pos.x < 0. || pos.y < 0. {
+		return true;
+	}
+	let x = pos.x as u32;
+	let y = pos.y as u32;
+
+	x >= image.width() as u32 || y >= image.height() as u32 || image.get_pixel(x, y).a > WALL_ALPHA
+}
+
+/// Checks if any pixel in a square of the arena is a wall, so walls thinner than the square are not missed.
+/// Only the part of the square inside the arena is checked.
+pub fn is_wall_cell(image: &Image, corner: UVec2, size: u32) -> bool {
+	let end = (corner + size).min(uvec2(image.width() as u32, image.height() as u32));
+	(corner.y..end.y).any(|y| (corner.x..end.x).any(|x| image.get_pixel(x, y).a > WALL_ALPHA))
+}
+
+const fn normal(num: f32) -> f32 {
+	num * f32::consts::FRAC_1_SQRT_2
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn normal_test() {
+		assert_eq!(11, normal(16.).round() as i32)
+	}
+
+	#[test]
+	fn stats() {
+		let stats = Stats {
+			top_speed: 2.,
+			acceleration: 0.5,
+			..Default::default()
+		};
+		let mut horse = Horse::new(
+			0,
+			Vec2::ZERO,
+			WinData::default(),
+			stats,
+			&RandGenerator::new(),
+		);
+		for _ in 0..10 {
+			horse.update();
+		}
+		assert_eq!(horse.speed.to_bits(), 2f32.to_bits());
+
+		horse.pos = Vec2::ZERO;
+		horse.stats.size = DIR_WIDTH * 2.;
+		assert!(horse.collision_carrots(vec2(DIR_WIDTH * 3., 0.)));
+		horse.stats.size = DIR_WIDTH;
+		assert!(!horse.collision_carrots(vec2(DIR_WIDTH * 3., 0.)));
+	}
+
+	#[test]
+	fn outside() {
+		let image = Image::gen_image_color(10, 10, BLANK);
+
+		assert!(!is_wall(&image, vec2(0., 0.)));
+		assert!(is_wall(&image, vec2(-1., 5.)));
+		assert!(is_wall(&image, vec2(5., -0.5)));
+		assert!(is_wall(&image, vec2(10., 5.)));
+	}
+
+	#[test]
+	fn probes() {
+		// The three probes along the top of the horse, touching a flat ceiling
+		let top = 1 << 0 | 1 << 3 | 1 << 4;
+		assert_eq!(probe_normal(top), Some(vec2(0., 1.)));
+		assert_eq!(probe_normal(1 << 4 | 1 << 5), Some(Vec2::ZERO));
+		assert_eq!(probe_normal(NO_COLLISION), None);
+	}
+
+	#[test]
+	fn twins() {
+		let horse = |id| {
+			Horse::new(
+				id,
+				Vec2::ZERO,
+				WinData::default(),
+				Stats::default(),
+				&RandGenerator::new(),
+			)
+		};
+		let (a, b) = (horse(0), horse(1));
+
+		// Identical horses in the same spot still run into each other, but not themselves
+		assert_eq!(a.dir, b.dir);
+		assert_ne!(a.collision_honses([&a, &b]), NO_COLLISION);
+		assert_eq!(a.collision_honses([&a]), NO_COLLISION);
+	}
+}
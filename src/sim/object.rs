@@ -0,0 +1,104 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Something placed in the arena that horses can run into.
+/// Declared in `[[objects]]` tables in a race file, with `kind` set to one of the [`Effect`]s.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Object {
+	pub pos: Vec2,
+	/// How close the center of a horse has to get to trigger it, in pixels.
+	/// Horses bounce off of solid objects as soon as their edge is this close instead.
+	pub radius: f32,
+	/// Points the object moves through after `pos`, before going back to it
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub path: Vec<Vec2>,
+	/// How fast the object moves along its path, in pixels per tick
+	#[serde(default)]
+	pub speed: f32,
+	#[serde(flatten)]
+	pub effect: Effect,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Effect {
+	/// Multiplies the speed of horses that run over it.
+	/// Classic bounces pick a new speed, which ends the boost, but reflecting off of things keeps it
+	Boost { factor: f32 },
+	/// Slows horses down to this speed while they are in it
+	Mud { max_speed: f32 },
+	/// Sends horses that run into it somewhere else
+	Teleporter { to: Vec2 },
+	/// Bounces horses off like a wall, multiplying their speed
+	Bumper { factor: f32 },
+	/// Bounces horses off like a wall
+	Obstacle,
+}
+
+impl Object {
+	/// If horses bounce off the object, instead of running over it.
+	pub fn is_solid(&self) -> bool {
+		matches!(self.effect, Effect::Bumper { .. } | Effect::Obstacle)
+	}
+
+	/// Where the object is after this many ticks.
+	/// Objects go around their path at a steady speed, so this is the same every race.
+	pub fn pos_at(&self, ticks: u32) -> Vec2 {
+		if self.path.is_empty() || self.speed <= 0. {
+			return self.pos;
+		}
+
+		let points = || {
+			let start = [self.pos];
+			start
+				.into_iter()
+				.chain(self.path.iter().copied())
+				.zip(self.path.iter().copied().chain(start))
+		};
+		let length = points().map(|(a, b)| a.distance(b)).sum::<f32>();
+		if length <= 0. {
+			return self.pos;
+		}
+
+		let mut travelled = (self.speed * ticks as f32) % length;
+		for (a, b) in points() {
+			let segment = a.distance(b);
+			if travelled <= segment {
+				return a.lerp(b, travelled / segment.max(f32::EPSILON));
+			}
+			travelled -= segment;
+		}
+		self.pos
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn path() {
+		let object = Object {
+			pos: vec2(0., 0.),
+			radius: 10.,
+			path: vec![vec2(10., 0.)],
+			speed: 2.5,
+			effect: Effect::Obstacle,
+		};
+
+		assert_eq!(object.pos_at(0), vec2(0., 0.));
+		assert_eq!(object.pos_at(2), vec2(5., 0.));
+		assert_eq!(object.pos_at(6), vec2(5., 0.));
+		assert_eq!(object.pos_at(8), vec2(0., 0.));
+	}
+
+	#[test]
+	fn from_toml() {
+		let object: Object =
+			toml::from_str("pos = [10, 20]\nradius = 5\nkind = \"teleporter\"\nto = [30, 40]\n")
+				.unwrap();
+
+		assert_eq!(object.effect, Effect::Teleporter { to: vec2(30., 40.) });
+		assert_eq!(object.pos_at(100), vec2(10., 20.));
+	}
+}
@@ -2,6 +2,9 @@ use std::{fmt::Debug, path::Path, sync::LazyLock};
 
 use hex_literal::hex;
 use macroquad::prelude::*;
+use tokio::{runtime::Runtime, task::JoinHandle};
+
+use crate::error::{Error, Result, warn};
 use tracing::Level;
 use tracing_subscriber::FmtSubscriber;
 
@@ -20,41 +23,40 @@ static DEBUG_IMG: LazyLock<Image> = LazyLock::new(|| {
 	}
 });
 
-/// Loads an image from a file.
-/// Avoids Macroquad's async, allowing it to be used in a multithreaded context.
-pub fn load_img_blocking<Dir: AsRef<Path> + Debug>(path: Dir) -> Image {
-	let bytes = match std::fs::read(&path) {
-		Ok(ok) => ok,
-		Err(e) => {
-			tracing::warn!("Image {path:?} could not be read: {e}");
-			return debug_img();
-		}
-	};
+static RUNTIME: LazyLock<Runtime> = LazyLock::new(|| {
+	Runtime::new().unwrap_or_else(|e| {
+		tracing::error!("Could not create runtime: {e}");
+		panic!()
+	})
+});
+
+/// Runs a blocking function on the shared Tokio runtime's thread pool.
+pub fn spawn_blocking<F, R>(f: F) -> JoinHandle<R>
+where
+	F: FnOnce() -> R + Send + 'static,
+	R: Send + 'static,
+{
+	RUNTIME.spawn_blocking(f)
+}
+
+/// Loads an image from a file, or returns why it could not be loaded.
+pub fn try_load_img(path: impl AsRef<Path>) -> Result<Image> {
+	let path = path.as_ref();
+	let bytes = std::fs::read(path).map_err(|e| Error::Read(path.into(), e))?;
+	let img =
+		Image::from_file_with_format(&bytes, None).map_err(|e| Error::Image(path.into(), e))?;
 
-	let img = match Image::from_file_with_format(&bytes, None) {
-		Ok(ok) => ok,
-		Err(e) => {
-			tracing::warn!("Image {path:?} failed to load: {e}");
-			return debug_img();
-		}
-	};
 	tracing::info!("Image {path:?} loaded!");
-	img
+	Ok(img)
 }
 
-/// Loads an image from a file asynchronously.
+/// Loads an image from a file, falling back to the debug image if it fails.
 /// Avoids Macroquad's async, allowing it to be used in a multithreaded context.
-pub async fn load_img<Dir>(path: Dir) -> Image
-where
-	Dir: AsRef<Path> + Debug + Send + 'static,
-{
-	match tokio::task::spawn_blocking(move || load_img_blocking(&path)).await {
-		Ok(ok) => ok,
-		Err(e) => {
-			tracing::error!("{e}");
-			debug_img()
-		}
-	}
+pub fn load_img_blocking<Dir: AsRef<Path> + Debug>(path: Dir) -> Image {
+	try_load_img(path).unwrap_or_else(|e| {
+		warn(&e);
+		debug_img()
+	})
 }
 
 /// Get the debug image.
@@ -75,11 +77,12 @@ pub fn render_texture_fullscreen(texture: &Texture2D) {
 	);
 }
 
-/// Initiates the log.
+/// Initiates the log, showing messages up to the given level.
 /// Logs an error if already called.
-pub fn init_log() {
+pub fn init_log(level: Level) {
 	let subscriber = FmtSubscriber::builder()
-		.with_max_level(Level::INFO)
+		.with_max_level(level)
+		.with_writer(std::io::stderr)
 		.finish();
 
 	tracing::subscriber::set_global_default(subscriber).unwrap_or_else(|_| {
@@ -0,0 +1,153 @@
+use std::{
+	path::{Path, PathBuf},
+	time::SystemTime,
+};
+
+use hashbrown::HashMap;
+use macroquad::prelude::*;
+
+use crate::{
+	Scene, assets,
+	cli::RaceArgs,
+	data::{self, RaceData},
+	error::warn,
+	race::{Loading, Race},
+};
+
+/// How often files are checked for changes, in seconds.
+const POLL_TIME: f32 = 0.5;
+
+/// Runs a race over and over, rebuilding it whenever one of its files changes.
+pub struct Watch {
+	args: RaceArgs,
+	/// The seed of the first race, reused unless the file or command line sets one
+	seed: Option<u64>,
+	/// Every file the race was built from, and when it was last modified
+	files: HashMap<PathBuf, Option<SystemTime>>,
+	timer: f32,
+	stage: Stage,
+}
+
+enum Stage {
+	Loading(Loading),
+	Race(Box<Race>),
+	/// Only used while moving between the other stages
+	Empty,
+}
+
+impl Watch {
+	pub fn new(args: RaceArgs) -> Self {
+		let data = RaceData::load(&args.path).with_overrides(&args).set_seed();
+		let mut watch = Self {
+			args,
+			seed: data.seed(),
+			files: HashMap::new(),
+			timer: 0.,
+			stage: Stage::Empty,
+		};
+		watch.start(data);
+		watch
+	}
+
+	/// Starts loading a race, and remembers which files to watch.
+	fn start(&mut self, data: RaceData) {
+		self.files = data
+			.files(&self.args.path)
+			.into_iter()
+			.map(|path| {
+				let modified = modified(&path);
+				(path, modified)
+			})
+			.collect();
+		self.stage = Stage::Loading(data.preload());
+	}
+
+	/// Reads the race file again and restarts the race.
+	/// If the file is broken, the current race keeps running until it is fixed.
+	/// Returns `false` if the file could not be read.
+	fn reload(&mut self) -> bool {
+		let data = match data::try_read::<RaceData>(&self.args.path) {
+			Ok(ok) => ok,
+			Err(e) => {
+				warn(&e);
+				return false;
+			}
+		};
+
+		tracing::info!("Reloading \"{}\"", self.args.path.display());
+		let mut data = data.with_overrides(&self.args);
+		if data.seed().is_none() {
+			data = data.override_seed(self.seed);
+		}
+		self.start(data.set_seed());
+		true
+	}
+
+	/// Forgets every file that changed since it was last checked.
+	/// Returns `true` if anything changed.
+	fn poll(&mut self) -> bool {
+		let mut changed = false;
+		for (path, last) in &mut self.files {
+			let modified = modified(path);
+			if modified != *last {
+				tracing::info!("\"{}\" changed", path.display());
+				assets::forget(path);
+				*last = modified;
+				changed = true;
+			}
+		}
+		changed
+	}
+}
+
+impl Scene for Watch {
+	fn update(&mut self) {
+		self.timer += get_frame_time();
+		if self.timer >= POLL_TIME {
+			self.timer = 0.;
+			if self.poll() {
+				self.reload();
+				return;
+			}
+		}
+
+		match &mut self.stage {
+			Stage::Loading(loading) => {
+				if loading.is_done() {
+					let Stage::Loading(loading) = std::mem::replace(&mut self.stage, Stage::Empty)
+					else {
+						unreachable!("Stage is known to be `Stage::Loading(_)`")
+					};
+					self.stage = Stage::Race(Box::new(loading.finish()));
+				}
+			}
+			Stage::Race(race) => {
+				race.update();
+				// Wait for the file to be fixed, instead of trying again every frame
+				if race.should_finish() && !self.reload() {
+					self.stage = Stage::Empty;
+				}
+			}
+			Stage::Empty => (),
+		}
+	}
+
+	fn draw(&self) {
+		match &self.stage {
+			Stage::Loading(loading) => loading.draw(),
+			Stage::Race(race) => race.draw(),
+			Stage::Empty => (),
+		}
+	}
+
+	fn should_finish(&self) -> bool {
+		false
+	}
+}
+
+/// When a file was last modified, or `None` if that cannot be found, such as when it is missing.
+fn modified(path: &Path) -> Option<SystemTime> {
+	std::fs::metadata(path)
+		.and_then(|metadata| metadata.modified())
+		.ok()
+}